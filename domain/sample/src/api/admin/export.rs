@@ -0,0 +1,7 @@
+use lambda::serve::serve_stream;
+use lambda_http::Error;
+use sample::{handler::admin::export, service::SampleService};
+
+fn main() -> Result<(), Error> {
+    serve_stream(SampleService::new, export)
+}
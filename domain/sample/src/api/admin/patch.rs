@@ -0,0 +1,7 @@
+use lambda::serve::serve;
+use lambda_http::Error;
+use sample::{handler::admin::patch, service::SampleService};
+
+fn main() -> Result<(), Error> {
+    serve(SampleService::new, patch)
+}
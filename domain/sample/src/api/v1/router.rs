@@ -0,0 +1,17 @@
+use lambda::{router::Router, serve::serve_response};
+use lambda_http::Error;
+use sample::{
+    route::{SampleRoute, CUSTOMER_ROUTES},
+    service::SampleService,
+};
+
+/// Every route of the API in a single function that shares the connection pool.
+fn main() -> Result<(), Error> {
+    serve_response(
+        || async {
+            let service = SampleService::new().await;
+            service.map(|service| (Router::new(CUSTOMER_ROUTES), service))
+        },
+        |(router, service), request| SampleRoute::dispatch(router, service, request),
+    )
+}
@@ -0,0 +1,171 @@
+use std::env;
+
+use lambda::{
+    auth::Access,
+    etag::{entity_tag, representation_tag, Tagged},
+    export::{Export, ExportFormat},
+    page::ApiPageRequest,
+    request::RequestExtension,
+    response::{ExportResponse, JsonResponse, Negotiated},
+    stream::{RowStream, StreamFormat},
+};
+use lambda_http::{http::header::VARY, Request};
+use model::{
+    error::ErrorResult,
+    idempotency::Idempotent,
+    page::{Page, PageRequest},
+};
+use once_cell::sync::Lazy;
+
+use crate::{
+    model::{SampleDetail, SampleExport, SampleList, SampleRequest, ENTITY},
+    service::SampleService,
+};
+
+/// The group of the admin user pool that may change samples, from `ADMIN_WRITE_ROLE`, e.g. `admin`.
+/// Without it every user of the pool may change samples, as before groups were checked.
+static WRITE: Lazy<Access> = Lazy::new(|| match env::var("ADMIN_WRITE_ROLE") {
+    Ok(role) if !role.is_empty() => Access::Role(Box::leak(role.into_boxed_str())),
+    _ => Access::Authenticated,
+});
+
+/// With `Accept: text/csv` or `application/x-ndjson` every sample that matches the query is
+/// exported with its translations instead of a page. An export above the size limit is a `413`
+/// that points to the streaming export.
+pub async fn page(
+    service: &SampleService,
+    request: Request,
+) -> Result<Negotiated<Page<SampleList>>, ErrorResult> {
+    let query = request.query_param("query");
+
+    if let Some(format) = ExportFormat::negotiate(&request) {
+        let mut export = Export::new(format, SampleExport::COLUMNS);
+        service
+            .export(&query, |batch| {
+                batch.iter().try_for_each(|sample| match format {
+                    ExportFormat::Csv => sample
+                        .rows()
+                        .iter()
+                        .try_for_each(|row| export.push_row(row)),
+                    ExportFormat::Ndjson => export.push_json(sample),
+                })
+            })
+            .await?;
+        let response = ExportResponse::new(export, "samples").header(VARY, "accept");
+
+        return Ok(Negotiated::Export(response));
+    }
+
+    let fields = request.fields::<SampleList>()?;
+    let page_request = PageRequest::read(&request)?;
+    let result = service.page(&query, &page_request).await?;
+    let response = JsonResponse::ok(result)
+        .fields(fields)
+        .header(VARY, "accept");
+
+    Ok(Negotiated::Json(response))
+}
+
+/// Streams every sample that matches the query with its translations as a JSON array or, with
+/// `Accept: application/x-ndjson`, as NDJSON. Unlike the export of `page` it is not bounded by
+/// the 6 MB response limit of Lambda. The function URL has no authorizer, so the bearer token is
+/// verified by the function. As with the API, every user of the user pool may read samples.
+pub async fn export(
+    service: &'static SampleService,
+    request: Request,
+) -> Result<RowStream, ErrorResult> {
+    request.authorize(Access::Authenticated)?;
+    let query = request.query_param("query");
+    let format = StreamFormat::negotiate(&request);
+    let rows = service.export_stream(query);
+
+    Ok(RowStream::new(format, rows).header(VARY, "accept"))
+}
+
+/// A retry with the same `Idempotency-Key` returns the sample that was created first, with the
+/// status it was first sent with and the same `Location`.
+pub async fn create(
+    service: &SampleService,
+    request: Request,
+) -> Result<JsonResponse<Idempotent<SampleDetail>>, ErrorResult> {
+    let user_id = request.authorize(*WRITE)?.sub;
+    let idempotency = request.idempotency_key()?;
+    let sample = request.validate_payload::<SampleRequest>()?;
+    let result = service
+        .create(sample, user_id, idempotency.as_ref())
+        .await?;
+    let (status, id) = match &result {
+        Idempotent::Fresh(sample) => (201, Some(sample.id)),
+        Idempotent::Replayed(replay) => (replay.status, replay.body["id"].as_i64()),
+    };
+    let path = request.uri().path().trim_end_matches('/');
+
+    match id {
+        Some(id) if status == 201 => Ok(JsonResponse::created(result, format!("{path}/{id}"))),
+        _ => Ok(JsonResponse::new(status, result)),
+    }
+}
+
+pub async fn get(
+    service: &SampleService,
+    request: Request,
+) -> Result<JsonResponse<Tagged<SampleDetail>>, ErrorResult> {
+    let id = request.path_param::<i64>("id")?;
+    let fields = request.fields::<SampleDetail>()?;
+    let languages = request.get_languages();
+    let result = service.get(id, false, &languages).await?;
+    let etag = representation_tag(result.id, result.version, None, fields.as_deref());
+    let status = if request.if_none_match(&etag) {
+        304
+    } else {
+        200
+    };
+
+    Ok(JsonResponse::new(status, Tagged::new(result, etag)).fields(fields))
+}
+
+pub async fn update(
+    service: &SampleService,
+    request: Request,
+) -> Result<(u16, Tagged<SampleDetail>), ErrorResult> {
+    let user_id = request.authorize(*WRITE)?.sub;
+    let id = request.path_param::<i64>("id")?;
+    let if_match = request.if_match(ENTITY, id)?;
+    let sample = request.validate_payload::<SampleRequest>()?;
+    let result = service.update(id, sample, if_match, user_id).await?;
+    let etag = entity_tag(result.id, result.version);
+
+    Ok((201, Tagged::new(result, etag)))
+}
+
+/// Changes only the members of the `application/merge-patch+json` body, e.g. `{"amount": 2}`.
+/// The `translations` are replaced as a whole, as arrays are in a merge patch.
+pub async fn patch(
+    service: &SampleService,
+    request: Request,
+) -> Result<(u16, Tagged<SampleDetail>), ErrorResult> {
+    let user_id = request.authorize(*WRITE)?.sub;
+    let id = request.path_param::<i64>("id")?;
+    let if_match = request.if_match(ENTITY, id)?;
+    let result = service
+        .patch(id, if_match, user_id, |current| {
+            request.validate_merge_patch(&SampleRequest::from(current))
+        })
+        .await?;
+    let etag = entity_tag(result.id, result.version);
+
+    Ok((200, Tagged::new(result, etag)))
+}
+
+pub async fn delete(
+    service: &SampleService,
+    request: Request,
+) -> Result<JsonResponse<()>, ErrorResult> {
+    let user_id = request.authorize(*WRITE)?.sub;
+    let id = request.path_param::<i64>("id")?;
+    let if_match = request.if_match(ENTITY, id)?;
+
+    service.delete(id, if_match, user_id).await?;
+
+    Ok(JsonResponse::no_content())
+}
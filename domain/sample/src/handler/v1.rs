@@ -0,0 +1,65 @@
+use lambda::{
+    etag::{representation_tag, Tagged},
+    language::Localized,
+    request::RequestExtension,
+    response::JsonResponse,
+    seek::ApiSeekRequest,
+};
+use lambda_http::Request;
+use model::{
+    error::ErrorResult,
+    limit::SizeLimit,
+    seek::{Seek, SeekRequest},
+};
+
+use crate::{
+    model::{SampleDetail, SampleList, SampleSeekFilter},
+    service::SampleService,
+};
+
+/// Customers scroll through the list, so fewer records are needed at a time than in admin.
+const SEEK_SIZE_LIMIT: SizeLimit = SizeLimit::new(20, 1, 50);
+
+pub async fn seek(
+    service: &SampleService,
+    request: Request,
+) -> Result<JsonResponse<Localized<Seek<SampleList>>>, ErrorResult> {
+    let fields = request.fields::<SampleList>()?;
+    let languages = request.get_languages();
+    let query = request.query_param("query");
+    let filter = &SampleSeekFilter { languages, query };
+    let seek_request = &SeekRequest::read_with(&request, SEEK_SIZE_LIMIT)?;
+    let result = service.seek(filter, seek_request).await?;
+    let served = result
+        .data
+        .iter()
+        .filter_map(|sample| sample.language.to_owned())
+        .collect::<Vec<_>>();
+
+    Ok(JsonResponse::ok(Localized::new(result, served)).fields(fields))
+}
+
+pub async fn get(
+    service: &SampleService,
+    request: Request,
+) -> Result<JsonResponse<Tagged<Localized<SampleDetail>>>, ErrorResult> {
+    let id = request.path_param::<i64>("id")?;
+    let fields = request.fields::<SampleDetail>()?;
+    let languages = request.get_languages();
+    let result = service.get(id, true, &languages).await?;
+    let served = result.language.to_owned();
+    let etag = representation_tag(
+        result.id,
+        result.version,
+        served.as_deref(),
+        fields.as_deref(),
+    );
+    let status = if request.if_none_match(&etag) {
+        304
+    } else {
+        200
+    };
+    let body = Tagged::new(Localized::new(result, served), etag);
+
+    Ok(JsonResponse::new(status, body).fields(fields))
+}
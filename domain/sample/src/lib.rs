@@ -1,3 +1,5 @@
+pub mod handler;
 pub mod model;
 pub mod repository;
+pub mod route;
 pub mod service;
@@ -9,15 +9,19 @@ use serde_trim::{option_string_trim, string_trim};
 use serde_with::skip_serializing_none;
 use sqlx::prelude::FromRow;
 use sqlx::types::Decimal;
-use time::{serde::rfc3339, OffsetDateTime};
+use time::{format_description::well_known::Rfc3339, serde::rfc3339, OffsetDateTime};
 use validator::{Validate, ValidationError};
 
+/// The name of the entity in errors.
+pub const ENTITY: &str = "sample";
+
 pub struct SampleSeekFilter {
-    pub language: Option<String>,
+    pub languages: Vec<String>,
     pub query: Option<String>,
 }
 
-#[derive(FromRow, Serialize)]
+/// `Deserialize` gives the field names that `fields` is checked against.
+#[derive(FromRow, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SampleList {
     pub id: i64,
@@ -27,6 +31,10 @@ pub struct SampleList {
     pub amount: Decimal,
     #[serde(with = "rfc3339")]
     pub created_at: OffsetDateTime,
+    /// The language of the translation that was served.
+    #[sqlx(default)]
+    #[serde(skip)]
+    pub language: Option<String>,
 }
 
 impl Seekable for SampleList {
@@ -39,7 +47,7 @@ impl Seekable for SampleList {
     }
 }
 
-#[derive(Debug, Validate, Deserialize)]
+#[derive(Debug, Validate, Serialize, Deserialize)]
 pub struct SampleRequest {
     #[serde(default, deserialize_with = "string_trim")]
     #[validate(length(min = 1, max = 100))]
@@ -59,7 +67,7 @@ pub struct SampleRequest {
     pub translations: Vec<SampleTranslation>,
 }
 
-#[derive(Debug, FromRow, Validate, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, FromRow, Validate, Serialize, Deserialize)]
 pub struct SampleTranslation {
     #[serde(default, deserialize_with = "string_trim")]
     #[validate(length(min = 1, max = 100))]
@@ -90,7 +98,7 @@ impl Translation for SampleTranslation {
 }
 
 #[skip_serializing_none]
-#[derive(FromRow, Serialize)]
+#[derive(FromRow, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SampleDetail {
     pub id: i64,
@@ -102,6 +110,109 @@ pub struct SampleDetail {
     pub translations: Option<Vec<SampleTranslation>>,
     #[serde(with = "rfc3339")]
     pub created_at: OffsetDateTime,
+    /// The language of the translation that was served.
+    #[sqlx(default)]
+    #[serde(skip)]
+    pub language: Option<String>,
+}
+
+/// The sample as the document that a merge patch is applied to.
+impl From<&SampleDetail> for SampleRequest {
+    fn from(sample: &SampleDetail) -> Self {
+        Self {
+            name: sample.name.to_owned(),
+            description: sample.description.to_owned(),
+            amount: sample.amount,
+            translations: sample.translations.to_owned().unwrap_or_default(),
+        }
+    }
+}
+
+/// The columns of a sample that a merge patch changed. `None` is a column that is unchanged.
+#[derive(Debug, Default, PartialEq)]
+pub struct SamplePatch {
+    pub name: Option<String>,
+    pub description: Option<Option<String>>,
+    pub amount: Option<Decimal>,
+}
+
+impl SamplePatch {
+    pub fn new(current: &SampleDetail, patched: &SampleRequest) -> Self {
+        Self {
+            name: (current.name != patched.name).then(|| patched.name.to_owned()),
+            description: (current.description != patched.description)
+                .then(|| patched.description.to_owned()),
+            amount: (current.amount != patched.amount).then_some(patched.amount),
+        }
+    }
+}
+
+/// A translation of the sample `id`, as read for many samples at once.
+#[derive(FromRow)]
+pub struct SampleTranslationRow {
+    pub id: i64,
+    #[sqlx(flatten)]
+    pub translation: SampleTranslation,
+}
+
+/// A sample with all of its translations, one line of an NDJSON export.
+#[derive(FromRow, Serialize)]
+pub struct SampleExport {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub sample: SampleList,
+    #[sqlx(json)]
+    pub translations: Vec<SampleTranslation>,
+}
+
+impl SampleExport {
+    /// The columns of a CSV export. The order must not change, spreadsheets rely on it.
+    pub const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "description",
+        "amount",
+        "createdAt",
+        "language",
+        "translationName",
+        "translationDescription",
+        "ordinal",
+    ];
+
+    /// The CSV rows of the sample, one per translation. A sample without translations is a
+    /// single row with empty translation columns.
+    pub fn rows(&self) -> Vec<Vec<String>> {
+        let sample = &self.sample;
+        let columns = [
+            sample.id.to_string(),
+            sample.name.to_owned(),
+            sample.description.to_owned().unwrap_or_default(),
+            sample.amount.to_string(),
+            sample.created_at.format(&Rfc3339).unwrap_or_default(),
+        ];
+
+        if self.translations.is_empty() {
+            let mut row = columns.to_vec();
+            row.resize(Self::COLUMNS.len(), String::new());
+
+            return vec![row];
+        }
+
+        self.translations
+            .iter()
+            .map(|translation| {
+                let mut row = columns.to_vec();
+                row.extend([
+                    translation.language.to_owned(),
+                    translation.name.to_owned(),
+                    translation.description.to_owned().unwrap_or_default(),
+                    translation.ordinal.to_string(),
+                ]);
+
+                row
+            })
+            .collect()
+    }
 }
 
 pub struct SampleTranslationsBinds {
@@ -143,3 +254,51 @@ fn validate_amount(amount: &Decimal) -> Result<(), ValidationError> {
 
     validate_decimal_range(amount, MIN, MAX)
 }
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+    use time::OffsetDateTime;
+
+    use super::{SampleExport, SampleList, SampleTranslation};
+
+    fn export(translations: Vec<SampleTranslation>) -> SampleExport {
+        SampleExport {
+            sample: SampleList {
+                id: 1,
+                name: "Sample".to_owned(),
+                description: None,
+                amount: dec!(1.50),
+                created_at: OffsetDateTime::UNIX_EPOCH,
+                language: None,
+            },
+            translations,
+        }
+    }
+
+    #[test]
+    fn rows_should_repeat_sample_per_translation() {
+        let translation = |language: &str, ordinal| SampleTranslation {
+            name: format!("Sample {language}"),
+            description: Some("Text".to_owned()),
+            language: language.to_owned(),
+            ordinal,
+        };
+        let rows = export(vec![translation("de", 1), translation("en", 2)]).rows();
+        let sample = ["1", "Sample", "", "1.50", "1970-01-01T00:00:00Z"];
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0][..5], sample);
+        assert_eq!(rows[0][5..], ["de", "Sample de", "Text", "1"]);
+        assert_eq!(rows[1][5..], ["en", "Sample en", "Text", "2"]);
+    }
+
+    #[test]
+    fn rows_without_translations_should_return_empty_columns() {
+        let rows = export(Vec::new()).rows();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].len(), SampleExport::COLUMNS.len());
+        assert!(rows[0][5..].iter().all(String::is_empty));
+    }
+}
@@ -2,32 +2,40 @@ use aws_config::{load_defaults, BehaviorVersion};
 use aws_sdk_secretsmanager::Client;
 use database::{
     error_parser::{database_error, resource_error},
-    postgres::connect_postgres,
+    postgres::{connect_postgres, query_span, ConnectError},
+};
+use futures_util::{
+    stream::{self, BoxStream},
+    StreamExt, TryStreamExt,
 };
 use model::{
-    error::{version_conflict, ErrorResult},
+    error::{id_not_found, version_conflict, ErrorResult},
     page::PageRequest,
+    precondition::IfMatch,
     seek::SeekRequest,
 };
 use sqlx::{query, query_as, PgPool, Postgres, Transaction};
+use time::OffsetDateTime;
+use tracing::Instrument;
 
 use crate::model::SampleTranslationsBinds;
 
-use super::model::{SampleDetail, SampleList, SampleRequest, SampleSeekFilter, SampleTranslation};
-
-static ENTITY: &str = "sample";
+use super::model::{
+    SampleDetail, SampleExport, SampleList, SamplePatch, SampleRequest, SampleSeekFilter,
+    SampleTranslation, SampleTranslationRow, ENTITY,
+};
 
 pub struct SampleRepository {
     pub db: PgPool,
 }
 
 impl SampleRepository {
-    pub async fn default() -> Self {
+    pub async fn new() -> Result<Self, ConnectError> {
         let config = load_defaults(BehaviorVersion::latest()).await;
         let secret_client = Client::new(&config);
-        let db = connect_postgres(&secret_client).await;
+        let db = connect_postgres(&secret_client).await?;
 
-        Self { db }
+        Ok(Self { db })
     }
 
     /// Seek / keyset pagination.
@@ -42,12 +50,13 @@ impl SampleRepository {
         static SQL: &str = include_str!("sql/seek.sql");
 
         query_as::<_, SampleList>(SQL)
-            .bind(&filter.language)
+            .bind(&filter.languages)
             .bind(&filter.query)
             .bind(seek_request.limit)
             .bind(seek_request.created_at)
             .bind(seek_request.id)
             .fetch_all(&self.db)
+            .instrument(query_span("seek.sql"))
             .await
             .map_err(database_error)
     }
@@ -64,16 +73,56 @@ impl SampleRepository {
             .bind(page_request.size)
             .bind(page_request.offset)
             .fetch_all(&self.db)
+            .instrument(query_span("page.sql"))
+            .await
+            .map_err(database_error)
+    }
+
+    /// The samples after the `created_at` and `id` of the last sample of the previous batch,
+    /// in the order of `page`. Keyset iteration does not skip or repeat a sample when one is
+    /// created during the export, unlike an offset.
+    pub async fn export(
+        &self,
+        query: &Option<String>,
+        size: i64,
+        last: Option<(OffsetDateTime, i64)>,
+    ) -> Result<Vec<SampleList>, ErrorResult> {
+        static SQL: &str = include_str!("sql/export.sql");
+
+        query_as::<_, SampleList>(SQL)
+            .bind(query)
+            .bind(size)
+            .bind(last.map(|(created_at, _)| created_at))
+            .bind(last.map(|(_, id)| id))
+            .fetch_all(&self.db)
+            .instrument(query_span("export.sql"))
             .await
             .map_err(database_error)
     }
 
+    /// Every sample that matches the query with its translations, in the order of `page`.
+    /// The rows are read from the cursor of the query as the stream is polled.
+    pub fn export_stream(
+        &self,
+        query: Option<String>,
+    ) -> BoxStream<'_, Result<SampleExport, ErrorResult>> {
+        static SQL: &str = include_str!("sql/export_stream.sql");
+        let span = query_span("export_stream.sql");
+        let mut rows = query_as::<_, SampleExport>(SQL).bind(query).fetch(&self.db);
+
+        // `Instrument` only covers futures, so the span is entered each time a row is polled.
+        stream::poll_fn(move |cx| span.in_scope(|| rows.poll_next_unpin(cx)))
+            .map_err(database_error)
+            .boxed()
+    }
+
     pub async fn count(&self, query: &Option<String>) -> Result<i64, ErrorResult> {
         static SQL: &str = include_str!("sql/count.sql");
 
         query_as(SQL)
             .bind(query)
             .fetch_one(&self.db)
+            .instrument(query_span("count.sql"))
             .await
             .map(|result: (i64,)| result.0)
             .map_err(database_error)
@@ -94,6 +143,7 @@ impl SampleRepository {
             .bind(&user_id)
             .bind(&user_id)
             .fetch_one(&mut **tx)
+            .instrument(query_span("create.sql"))
             .await
             .map_err(database_error)
     }
@@ -102,53 +152,119 @@ impl SampleRepository {
         &self,
         id: i64,
         translate: bool,
-        language: &Option<String>,
+        languages: &[String],
     ) -> Result<SampleDetail, ErrorResult> {
         static SQL: &str = include_str!("sql/get.sql");
 
         query_as::<_, SampleDetail>(SQL)
             .bind(id)
             .bind(translate)
-            .bind(language)
+            .bind(languages)
             .fetch_one(&self.db)
+            .instrument(query_span("get.sql"))
             .await
             .map_err(|error| resource_error(ENTITY, id, None, error))
     }
 
+    /// The sample with its translations, locked until the end of the transaction so that a
+    /// change can be applied to what it reads.
+    pub async fn get_for_update(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        id: i64,
+    ) -> Result<SampleDetail, ErrorResult> {
+        static SQL: &str = include_str!("sql/get_for_update.sql");
+        static TRANSLATIONS_SQL: &str = include_str!("sql/translations_list.sql");
+
+        let mut sample = query_as::<_, SampleDetail>(SQL)
+            .bind(id)
+            .fetch_one(&mut **tx)
+            .instrument(query_span("get_for_update.sql"))
+            .await
+            .map_err(|error| resource_error(ENTITY, id, None, error))?;
+        sample.translations = query_as::<_, SampleTranslation>(TRANSLATIONS_SQL)
+            .bind(id)
+            .fetch_all(&mut **tx)
+            .instrument(query_span("translations_list.sql"))
+            .await
+            .map(Some)
+            .map_err(database_error)?;
+
+        Ok(sample)
+    }
+
+    /// Updates the sample when its version is one of `if_match`.
     pub async fn update(
         &self,
         tx: &mut Transaction<'_, Postgres>,
         id: i64,
         sample: &SampleRequest,
-        version: i16,
+        if_match: &IfMatch,
         user_id: String,
     ) -> Result<SampleDetail, ErrorResult> {
         static SQL: &str = include_str!("sql/update.sql");
 
         query_as::<_, SampleDetail>(SQL)
             .bind(id)
-            .bind(version)
+            .bind(if_match.versions())
             .bind(&sample.name)
             .bind(&sample.description)
             .bind(sample.amount)
             .bind(user_id)
             .fetch_one(&mut **tx)
+            .instrument(query_span("update.sql"))
+            .await
+            .map_err(|error| resource_error(ENTITY, id, if_match.version(), error))
+    }
+
+    /// Updates only the columns that are `Some`, under the same version check as `update`.
+    pub async fn patch(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        id: i64,
+        patch: &SamplePatch,
+        version: i16,
+        user_id: String,
+    ) -> Result<SampleDetail, ErrorResult> {
+        static SQL: &str = include_str!("sql/patch.sql");
+
+        query_as::<_, SampleDetail>(SQL)
+            .bind(id)
+            .bind(version)
+            .bind(patch.name.is_some())
+            .bind(&patch.name)
+            .bind(patch.description.is_some())
+            .bind(patch.description.to_owned().flatten())
+            .bind(patch.amount.is_some())
+            .bind(patch.amount)
+            .bind(user_id)
+            .fetch_one(&mut **tx)
+            .instrument(query_span("patch.sql"))
             .await
             .map_err(|error| resource_error(ENTITY, id, Some(version), error))
     }
 
-    pub async fn delete(&self, id: i64, version: i16, user_id: String) -> Result<(), ErrorResult> {
+    pub async fn delete(
+        &self,
+        id: i64,
+        if_match: &IfMatch,
+        user_id: String,
+    ) -> Result<(), ErrorResult> {
         static SQL: &str = include_str!("sql/delete.sql");
         let result = query(SQL)
             .bind(id)
-            .bind(version)
+            .bind(if_match.versions())
             .bind(user_id)
             .execute(&self.db)
+            .instrument(query_span("delete.sql"))
             .await
-            .map_err(|error| resource_error(ENTITY, id, Some(version), error))?;
+            .map_err(|error| resource_error(ENTITY, id, if_match.version(), error))?;
 
         if result.rows_affected() == 0 {
-            return Err(version_conflict(ENTITY, id, version));
+            return Err(match if_match.version() {
+                Some(version) => version_conflict(ENTITY, id, version),
+                None => id_not_found(ENTITY, id),
+            });
         }
 
         Ok(())
@@ -160,6 +276,22 @@ impl SampleRepository {
         query_as::<_, SampleTranslation>(SQL)
             .bind(id)
             .fetch_all(&self.db)
+            .instrument(query_span("translations_list.sql"))
+            .await
+            .map_err(database_error)
+    }
+
+    /// The translations of the samples `ids`, ordered by sample and ordinal.
+    pub async fn export_translations(
+        &self,
+        ids: &[i64],
+    ) -> Result<Vec<SampleTranslationRow>, ErrorResult> {
+        static SQL: &str = include_str!("sql/translations_export.sql");
+
+        query_as::<_, SampleTranslationRow>(SQL)
+            .bind(ids)
+            .fetch_all(&self.db)
+            .instrument(query_span("translations_export.sql"))
             .await
             .map_err(database_error)
     }
@@ -180,6 +312,7 @@ impl SampleRepository {
             .bind(binds.languages)
             .bind(binds.ordinals)
             .fetch_all(&mut **tx)
+            .instrument(query_span("translations_create.sql"))
             .await
             .map_err(database_error)
     }
@@ -190,25 +323,51 @@ impl SampleRepository {
         id: i64,
         translations: Vec<SampleTranslation>,
     ) -> Result<Vec<SampleTranslation>, ErrorResult> {
-        static SQL_DELETE: &str = include_str!("sql/translations_delete.sql");
-        let binds = SampleTranslationsBinds::from(translations);
+        let languages = translations
+            .iter()
+            .map(|translation| translation.language.to_owned())
+            .collect::<Vec<_>>();
+
+        self.delete_translations(tx, id, &languages).await?;
+        self.upsert_translations(tx, id, translations).await
+    }
 
-        query(SQL_DELETE)
+    /// Deletes every translation of the sample that is not in one of the `languages` to keep.
+    pub async fn delete_translations(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        id: i64,
+        languages: &[String],
+    ) -> Result<(), ErrorResult> {
+        static SQL: &str = include_str!("sql/translations_delete.sql");
+
+        query(SQL)
             .bind(id)
-            .bind(&binds.languages)
+            .bind(languages)
             .execute(&mut **tx)
+            .instrument(query_span("translations_delete.sql"))
             .await
-            .map_err(database_error)?;
+            .map(|_| ())
+            .map_err(database_error)
+    }
 
-        static SQL_UPSERT: &str = include_str!("sql/translations_upsert.sql");
+    pub async fn upsert_translations(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        id: i64,
+        translations: Vec<SampleTranslation>,
+    ) -> Result<Vec<SampleTranslation>, ErrorResult> {
+        static SQL: &str = include_str!("sql/translations_upsert.sql");
+        let binds = SampleTranslationsBinds::from(translations);
 
-        query_as::<_, SampleTranslation>(SQL_UPSERT)
+        query_as::<_, SampleTranslation>(SQL)
             .bind(id)
             .bind(binds.names)
             .bind(binds.descriptions)
             .bind(binds.languages)
             .bind(binds.ordinals)
             .fetch_all(&mut **tx)
+            .instrument(query_span("translations_upsert.sql"))
             .await
             .map_err(database_error)
     }
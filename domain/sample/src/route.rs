@@ -0,0 +1,70 @@
+use lambda::{
+    json::json_handler,
+    router::{not_found, Router},
+};
+use lambda_http::{Body, Error, Request, Response};
+
+use crate::{
+    handler::{admin, v1},
+    service::SampleService,
+};
+
+/// Every sample handler, one per function deployed by the stacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleRoute {
+    AdminPage,
+    AdminCreate,
+    AdminGet,
+    AdminUpdate,
+    AdminPatch,
+    AdminDelete,
+    V1Seek,
+    V1Get,
+}
+
+/// Same route keys as `stack/admin/AdminApi.ts`.
+pub const ADMIN_ROUTES: &[(&str, SampleRoute)] = &[
+    ("GET /api/admin/samples", SampleRoute::AdminPage),
+    ("POST /api/admin/samples", SampleRoute::AdminCreate),
+    ("GET /api/admin/samples/{id}", SampleRoute::AdminGet),
+    ("PUT /api/admin/samples/{id}", SampleRoute::AdminUpdate),
+    ("PATCH /api/admin/samples/{id}", SampleRoute::AdminPatch),
+    ("DELETE /api/admin/samples/{id}", SampleRoute::AdminDelete),
+];
+
+/// Same route keys as `stack/customer/CustomerApi.ts`.
+pub const CUSTOMER_ROUTES: &[(&str, SampleRoute)] = &[
+    ("GET /api/v1/samples", SampleRoute::V1Seek),
+    ("GET /api/v1/samples/{id}", SampleRoute::V1Get),
+];
+
+impl SampleRoute {
+    /// Runs the handler of the matched route or answers with `404` when none matches.
+    pub async fn dispatch(
+        router: &Router<Self>,
+        service: &SampleService,
+        request: Request,
+    ) -> Result<Response<Body>, Error> {
+        match router.route(request) {
+            (Some(route), request) => route.handle(service, request).await,
+            (None, request) => json_handler(request, not_found).await,
+        }
+    }
+
+    pub async fn handle(
+        self,
+        service: &SampleService,
+        request: Request,
+    ) -> Result<Response<Body>, Error> {
+        match self {
+            Self::AdminPage => json_handler(request, |r| admin::page(service, r)).await,
+            Self::AdminCreate => json_handler(request, |r| admin::create(service, r)).await,
+            Self::AdminGet => json_handler(request, |r| admin::get(service, r)).await,
+            Self::AdminUpdate => json_handler(request, |r| admin::update(service, r)).await,
+            Self::AdminPatch => json_handler(request, |r| admin::patch(service, r)).await,
+            Self::AdminDelete => json_handler(request, |r| admin::delete(service, r)).await,
+            Self::V1Seek => json_handler(request, |r| v1::seek(service, r)).await,
+            Self::V1Get => json_handler(request, |r| v1::get(service, r)).await,
+        }
+    }
+}
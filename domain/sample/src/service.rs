@@ -1,28 +1,47 @@
+use std::collections::HashMap;
+
+use futures_util::stream::BoxStream;
 use tokio::try_join;
+use tracing::{error, instrument};
 
-use database::postgres::{begin, commit};
+use database::{
+    idempotency::{claim_key, save_response},
+    postgres::{begin, commit, ConnectError},
+};
 use model::{
-    error::ErrorResult,
+    error::{internal_server, version_conflict, ErrorResult},
+    idempotency::{IdempotencyKey, Idempotent},
     page::{Page, PageRequest},
+    precondition::IfMatch,
     seek::{Seek, SeekRequest},
 };
 
 use super::{
-    model::{SampleDetail, SampleList, SampleRequest, SampleSeekFilter},
+    model::{
+        SampleDetail, SampleExport, SampleList, SamplePatch, SampleRequest, SampleSeekFilter,
+        ENTITY,
+    },
     repository::SampleRepository,
 };
 
+/// The scope of the idempotency keys of `create`.
+const CREATE_SCOPE: &str = "sample.create";
+
+/// The number of samples that `export` reads at a time.
+const EXPORT_BATCH_SIZE: i64 = 500;
+
 pub struct SampleService {
     pub repository: SampleRepository,
 }
 
 impl SampleService {
-    pub async fn default() -> Self {
-        let repository = SampleRepository::default().await;
+    pub async fn new() -> Result<Self, ConnectError> {
+        let repository = SampleRepository::new().await?;
 
-        Self { repository }
+        Ok(Self { repository })
     }
 
+    #[instrument(skip_all)]
     pub async fn seek(
         &self,
         filter: &SampleSeekFilter,
@@ -33,6 +52,7 @@ impl SampleService {
         Ok(Seek::new(list, seek_request))
     }
 
+    #[instrument(skip_all)]
     pub async fn page(
         &self,
         query: &Option<String>,
@@ -46,33 +66,110 @@ impl SampleService {
         Ok(Page::new(list, count, page_request))
     }
 
+    /// Every sample that matches the query, in the order of `page`, with all of its
+    /// translations. The samples are passed to `write` a batch at a time, and an error of
+    /// `write`, e.g. an `Export` above its size limit, stops reading further batches.
+    #[instrument(skip_all)]
+    pub async fn export(
+        &self,
+        query: &Option<String>,
+        mut write: impl FnMut(Vec<SampleExport>) -> Result<(), ErrorResult>,
+    ) -> Result<(), ErrorResult> {
+        let mut last = None;
+
+        loop {
+            let list = self
+                .repository
+                .export(query, EXPORT_BATCH_SIZE, last)
+                .await?;
+            let Some(tail) = list.last() else {
+                return Ok(());
+            };
+            last = Some((tail.created_at, tail.id));
+            let done = (list.len() as i64) < EXPORT_BATCH_SIZE;
+
+            let ids = list.iter().map(|sample| sample.id).collect::<Vec<_>>();
+            let mut translations = HashMap::<i64, Vec<_>>::new();
+            for row in self.repository.export_translations(&ids).await? {
+                translations
+                    .entry(row.id)
+                    .or_default()
+                    .push(row.translation);
+            }
+
+            let batch = list
+                .into_iter()
+                .map(|sample| SampleExport {
+                    translations: translations.remove(&sample.id).unwrap_or_default(),
+                    sample,
+                })
+                .collect();
+            write(batch)?;
+
+            if done {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Same as `export` as a stream of the samples, which are read as the stream is polled.
+    pub fn export_stream(
+        &self,
+        query: Option<String>,
+    ) -> BoxStream<'_, Result<SampleExport, ErrorResult>> {
+        self.repository.export_stream(query)
+    }
+
+    /// With an `Idempotency-Key` the created sample is stored as the response of the key in the
+    /// same transaction, so that a retry returns it instead of creating another sample.
+    #[instrument(skip_all)]
     pub async fn create(
         &self,
         request: SampleRequest,
         user_id: String,
-    ) -> Result<SampleDetail, ErrorResult> {
+        idempotency: Option<&IdempotencyKey>,
+    ) -> Result<Idempotent<SampleDetail>, ErrorResult> {
         let mut tx = begin(&self.repository.db).await?;
-        let mut sample = self.repository.create(&mut tx, &request, user_id).await?;
+
+        if let Some(key) = idempotency {
+            if let Some(replay) = claim_key(&mut tx, CREATE_SCOPE, &user_id, key).await? {
+                return Ok(Idempotent::Replayed(replay));
+            }
+        }
+
+        let mut sample = self
+            .repository
+            .create(&mut tx, &request, user_id.to_owned())
+            .await?;
         sample.translations = self
             .repository
             .create_translations(&mut tx, sample.id, request.translations)
             .await
             .map(Some)?;
 
+        if let Some(key) = idempotency {
+            let response = serde_json::to_value(&sample).map_err(|err| {
+                error!(target: "idempotency", "Unable to serialize the response. {:?}", err);
+                internal_server()
+            })?;
+            save_response(&mut tx, CREATE_SCOPE, &user_id, key, 201, &response).await?;
+        }
+
         commit(tx).await?;
 
-        Ok(sample)
+        Ok(Idempotent::Fresh(sample))
     }
 
     /// Gets a single sample record and returns the result.
-    /// If `translate` is true, `language` will be used to get the first translation applicable.
+    /// If `translate` is true, the translation in the most preferred of `languages` is used.
+    #[instrument(skip_all, fields(id = id))]
     pub async fn get(
         &self,
         id: i64,
         translate: bool,
-        language: &Option<String>,
+        languages: &[String],
     ) -> Result<SampleDetail, ErrorResult> {
-        let sample_fut = self.repository.get(id, translate, language);
+        let sample_fut = self.repository.get(id, translate, languages);
 
         if translate {
             return sample_fut.await;
@@ -85,17 +182,18 @@ impl SampleService {
         Ok(sample)
     }
 
+    #[instrument(skip_all, fields(id = id))]
     pub async fn update(
         &self,
         id: i64,
         request: SampleRequest,
-        version: i16,
+        if_match: IfMatch,
         user_id: String,
     ) -> Result<SampleDetail, ErrorResult> {
         let mut tx = begin(&self.repository.db).await?;
         let mut sample = self
             .repository
-            .update(&mut tx, id, &request, version, user_id)
+            .update(&mut tx, id, &request, &if_match, user_id)
             .await?;
         sample.translations = self
             .repository
@@ -108,7 +206,94 @@ impl SampleService {
         Ok(sample)
     }
 
-    pub async fn delete(&self, id: i64, version: i16, user_id: String) -> Result<(), ErrorResult> {
-        self.repository.delete(id, version, user_id).await
+    /// Writes the merge patch that `apply` makes of the current sample, which is read and locked
+    /// in the same transaction. Only the changed columns and the added, changed or removed
+    /// translations are written, under the same version check. A patch without changes writes
+    /// nothing and keeps the version.
+    #[instrument(skip_all, fields(id = id))]
+    pub async fn patch(
+        &self,
+        id: i64,
+        if_match: IfMatch,
+        user_id: String,
+        apply: impl FnOnce(&SampleDetail) -> Result<SampleRequest, ErrorResult>,
+    ) -> Result<SampleDetail, ErrorResult> {
+        let mut tx = begin(&self.repository.db).await?;
+        let current = self.repository.get_for_update(&mut tx, id).await?;
+
+        if !if_match.matches(current.version) {
+            return Err(version_conflict(
+                ENTITY,
+                id,
+                if_match.version().unwrap_or_default(),
+            ));
+        }
+
+        let patched = apply(&current)?;
+        let changes = SamplePatch::new(&current, &patched);
+        let translations = current.translations.as_deref().unwrap_or_default();
+        let removed = translations.iter().any(|translation| {
+            patched
+                .translations
+                .iter()
+                .all(|patched| patched.language != translation.language)
+        });
+        let (unchanged, changed) = patched
+            .translations
+            .iter()
+            .cloned()
+            .partition::<Vec<_>, _>(|translation| translations.contains(translation));
+
+        if changes == SamplePatch::default() && !removed && changed.is_empty() {
+            commit(tx).await?;
+            return Ok(current);
+        }
+
+        let mut sample = self
+            .repository
+            .patch(&mut tx, id, &changes, current.version, user_id)
+            .await?;
+
+        if removed {
+            let languages = patched
+                .translations
+                .iter()
+                .map(|translation| translation.language.to_owned())
+                .collect::<Vec<_>>();
+            self.repository
+                .delete_translations(&mut tx, id, &languages)
+                .await?;
+        }
+
+        let mut stored = unchanged;
+
+        if !changed.is_empty() {
+            let upserted = self
+                .repository
+                .upsert_translations(&mut tx, id, changed)
+                .await?;
+            stored.extend(upserted);
+        }
+
+        commit(tx).await?;
+        stored.sort_by_key(|translation| {
+            patched
+                .translations
+                .iter()
+                .position(|patched| patched.language == translation.language)
+        });
+        sample.translations = Some(stored);
+
+        Ok(sample)
+    }
+
+    #[instrument(skip_all, fields(id = id))]
+    pub async fn delete(
+        &self,
+        id: i64,
+        if_match: IfMatch,
+        user_id: String,
+    ) -> Result<(), ErrorResult> {
+        self.repository.delete(id, &if_match, user_id).await
     }
 }
@@ -3,4 +3,4 @@ set
     version = version + 1,
     deleted_by = $3,
     deleted_at = now()
-where id = $1 and version = $2 and deleted_at is null
\ No newline at end of file
+where id = $1 and ($2::smallint[] is null or version = any($2)) and deleted_at is null
\ No newline at end of file
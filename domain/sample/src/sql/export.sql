@@ -0,0 +1,8 @@
+select id, name, description, amount, created_at
+from sample
+where
+    deleted_at is null
+    and name ilike concat('%%', $1::text, '%%')
+    and ($3::timestamptz is null or $4::bigint is null or (created_at, id) < ($3, $4))
+order by created_at desc, id desc
+limit $2
\ No newline at end of file
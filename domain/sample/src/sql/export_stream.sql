@@ -0,0 +1,17 @@
+select s.id, s.name, s.description, s.amount, s.created_at, coalesce(t.translations, '[]') as translations
+from sample s
+left join lateral (
+    select json_agg(
+        json_build_object(
+            'name', name,
+            'description', description,
+            'language', language,
+            'ordinal', ordinal
+        )
+        order by ordinal
+    ) as translations
+    from sample_translation
+    where id = s.id
+) t on true
+where deleted_at is null and s.name ilike concat('%%', $1::text, '%%')
+order by s.created_at desc, s.id desc
\ No newline at end of file
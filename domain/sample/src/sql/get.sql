@@ -4,13 +4,14 @@ select
     coalesce(t.description, s.description) description,
     amount,
     version,
-    created_at
+    created_at,
+    t.language
 from sample s
 left join lateral (
-    select name, description
+    select name, description, language
     from sample_translation
     where id = s.id
-    order by (language = $3)::int desc, ordinal
+    order by array_position($3::text[], lower(language)) nulls last, ordinal
     limit 1
 ) t on $2
 where id = $1 and deleted_at is null
\ No newline at end of file
@@ -0,0 +1,4 @@
+select id, name, description, amount, version, created_at
+from sample
+where id = $1 and deleted_at is null
+for update
\ No newline at end of file
@@ -0,0 +1,10 @@
+update sample
+set
+    name = case when $3 then $4 else name end,
+    description = case when $5 then $6 else description end,
+    amount = case when $7 then $8 else amount end,
+    version = version + 1,
+    last_modified_at = now(),
+    last_modified_by = $9
+where id = $1 and version = $2
+returning id, name, description, amount, version, created_at
\ No newline at end of file
@@ -1,10 +1,10 @@
-select s.id, t.name, t.description, amount, created_at
+select s.id, t.name, t.description, amount, created_at, t.language
 from sample s
 left join lateral (
-    select name, description
+    select name, description, language
     from sample_translation
     where id = s.id
-    order by (language = $1)::int desc, ordinal
+    order by array_position($1::text[], lower(language)) nulls last, ordinal
     limit 1
 ) t on true
 where
@@ -0,0 +1,4 @@
+select id, name, description, language, ordinal
+from sample_translation
+where id = any($1)
+order by id, ordinal
\ No newline at end of file
@@ -6,5 +6,5 @@ set
     version = version + 1,
     last_modified_at = now(),
     last_modified_by = $6
-where id = $1 and version = $2
+where id = $1 and ($2::smallint[] is null or version = any($2))
 returning id, name, description, amount, version, created_at
\ No newline at end of file
@@ -1,28 +1,53 @@
 use convert_case::{Case, Casing};
 use model::error::{
-    id_not_found, internal_server, version_conflict, ErrorDetail, ErrorResult, ErrorSource,
+    id_not_found, internal_server, version_conflict, ErrorCode, ErrorDetail, ErrorResult,
+    ErrorSource,
 };
 use once_cell::sync::Lazy;
 use regex::Regex;
 use sqlx::{
     error::{
-        DatabaseError,
+        DatabaseError, ErrorKind,
         ErrorKind::{CheckViolation, ForeignKeyViolation, NotNullViolation, UniqueViolation},
     },
     postgres::PgDatabaseError,
     Error,
 };
-use std::vec;
 use tracing::error;
 
-static UNIQUE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"Key \((?:lower\()?([a-zA-Z0-9, ]+)+(?:::text)?\)")
-        .expect("UNIQUE_REGEX is not a valid pattern")
+static KEY_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"Key \((?:lower\()?([a-zA-Z0-9_, ]+)+(?:::text)?\)")
+        .expect("KEY_REGEX is not a valid pattern")
 });
 
+/// The parts of a `PgDatabaseError` that are needed to build an `ErrorDetail`.
+/// See https://www.postgresql.org/docs/current/protocol-error-fields.html
+#[derive(Debug)]
+struct Violation<'a> {
+    kind: ErrorKind,
+    table: Option<&'a str>,
+    column: Option<&'a str>,
+    constraint: Option<&'a str>,
+    detail: Option<&'a str>,
+}
+
+impl<'a> From<&'a PgDatabaseError> for Violation<'a> {
+    fn from(err: &'a PgDatabaseError) -> Self {
+        Self {
+            kind: err.kind(),
+            table: err.table(),
+            column: err.column(),
+            constraint: err.constraint(),
+            detail: err.detail(),
+        }
+    }
+}
+
+/// The error of a query on the record `id` of the `entity`. A row that is still referenced is
+/// reported on the `entity`, because postgres only names the table that references it.
 pub fn resource_error(entity: &str, id: i64, version: Option<i16>, err: Error) -> ErrorResult {
     if !matches!(err, Error::RowNotFound) {
-        return database_error(err);
+        return violation_error(Some(entity), err);
     }
 
     match version {
@@ -32,89 +57,362 @@ pub fn resource_error(entity: &str, id: i64, version: Option<i16>, err: Error) -
 }
 
 pub fn database_error(err: Error) -> ErrorResult {
-    let (status, detail) = match err.as_database_error() {
-        Some(err) => parse_detail(err.downcast_ref()),
+    violation_error(None, err)
+}
+
+fn violation_error(entity: Option<&str>, err: Error) -> ErrorResult {
+    match err.as_database_error() {
+        Some(err) => {
+            let violation = Violation::from(err.downcast_ref::<PgDatabaseError>());
+            parse_detail(&violation, entity).into()
+        }
         None => {
             error!(target: "database_error", "Something failed in the database. {:?}", err);
-            return internal_server();
+            internal_server()
         }
-    };
-
-    ErrorResult {
-        status,
-        errors: vec![detail],
     }
 }
 
-fn parse_detail(err: &PgDatabaseError) -> (u16, ErrorDetail) {
-    let (status, code, pointer) = match err.kind() {
+fn parse_detail(err: &Violation, entity: Option<&str>) -> ErrorDetail {
+    let (code, pointer) = match err.kind {
         UniqueViolation => unique_violation(err),
-        ForeignKeyViolation => foreign_key_violation(err),
+        ForeignKeyViolation => foreign_key_violation(err, entity),
         NotNullViolation => not_null_violation(err),
         CheckViolation => check_violation(err),
         _ => other_violation(err),
     };
-    let error = ErrorDetail {
+    ErrorDetail {
         id: None,
         code,
+        message: None,
         source: ErrorSource {
             pointer: Some(pointer),
             parameter: None,
             header: None,
             meta: None,
         },
-    };
+    }
+}
+
+fn unique_violation(err: &Violation) -> (ErrorCode, String) {
+    let code = ErrorCode::Duplicate;
+    let field = key_field(err);
+
+    if field.is_none() {
+        error!(target: "unique_violation", "Unique violation but no field found. {:?}", err);
+    }
 
-    (status, error)
+    (code, pointer(err.table, field))
 }
 
-fn unique_violation(err: &PgDatabaseError) -> (u16, String, String) {
-    let status = 409;
-    let code = "duplicate".to_owned();
-    let mut pointer = "/data".to_owned();
-    let table = match err.table() {
-        Some(table) => table,
-        None => {
-            error!(target: "unique_violation", "Unique violation but no table defined. {:?}", err);
-            return (status, code, pointer);
-        }
-    };
-    let detail = match err.detail() {
-        Some(detail) => detail,
-        None => {
-            error!(target: "unique_violation", "Unique violation but no detail defined. {:?}", err);
-            return (status, code, pointer);
-        }
+/// Either the referenced row does not exist (insert/update on the referencing table)
+/// or the row is still referenced by another table (update/delete on the referenced table).
+/// In both cases the key is a column of the table that was changed, which for a referenced row
+/// is the `entity`, not the referencing table that postgres reports.
+fn foreign_key_violation(err: &Violation, entity: Option<&str>) -> (ErrorCode, String) {
+    let referenced = err
+        .detail
+        .map(|detail| detail.contains("is still referenced"))
+        .unwrap_or(false);
+    let (code, table) = if referenced {
+        (ErrorCode::Referenced, entity)
+    } else {
+        (ErrorCode::ReferenceNotFound, err.table)
     };
-    let matched = UNIQUE_REGEX
-        .captures(detail)
-        .and_then(|m| m.get(1))
-        .and_then(|s| s.as_str().split(", ").last())
-        .map(|s| s.to_case(Case::Camel));
-    let field = match matched {
-        Some(field) => field,
-        None => {
-            error!(target: "unique_violation", "Unique violation but no field found. {:?}", err);
-            return (status, code, pointer);
-        }
-    };
-    pointer = format!("{pointer}/{table}/{field}");
+    let field = key_field(err);
+
+    if field.is_none() || table.is_none() {
+        error!(target: "foreign_key_violation", "Foreign key violation but no field found. {:?}", err);
+    }
+
+    (code, pointer(table, field))
+}
+
+fn not_null_violation(err: &Violation) -> (ErrorCode, String) {
+    let code = ErrorCode::Required;
+    let field = err.column.map(|column| column.to_case(Case::Camel));
+
+    if field.is_none() {
+        error!(target: "not_null_violation", "Not null violation but no column defined. {:?}", err);
+    }
 
-    (status, code, pointer)
+    (code, pointer(err.table, field))
 }
 
-fn foreign_key_violation(_: &PgDatabaseError) -> (u16, String, String) {
-    todo!()
+/// A value that the database rejected is `invalid` like a payload that fails validation, but with
+/// a `422`, because the request itself was well-formed.
+const CHECK_VIOLATION: ErrorCode = ErrorCode::custom("invalid", 422);
+
+/// Check constraints do not report a column, so the field is taken from the constraint name
+/// when it follows the default postgres naming convention of `{table}_{column}_check`.
+fn check_violation(err: &Violation) -> (ErrorCode, String) {
+    let code = CHECK_VIOLATION;
+    let field = err
+        .column
+        .or_else(|| constraint_column(err))
+        .map(|column| column.to_case(Case::Camel));
+
+    if field.is_none() {
+        error!(target: "check_violation", "Check violation but no field found. {:?}", err);
+    }
+
+    (code, pointer(err.table, field))
 }
 
-fn not_null_violation(_: &PgDatabaseError) -> (u16, String, String) {
-    todo!()
+fn other_violation(err: &Violation) -> (ErrorCode, String) {
+    error!(target: "other_violation", "Unhandled database violation. {:?}", err);
+
+    let code = ErrorCode::ServerInternal;
+    let field = err.column.map(|column| column.to_case(Case::Camel));
+
+    (code, pointer(err.table, field))
 }
 
-fn check_violation(_: &PgDatabaseError) -> (u16, String, String) {
-    todo!()
+fn key_field(err: &Violation) -> Option<String> {
+    err.detail
+        .and_then(|detail| KEY_REGEX.captures(detail))
+        .and_then(|m| m.get(1))
+        .and_then(|s| s.as_str().split(", ").last())
+        .map(|s| s.to_case(Case::Camel))
 }
 
-fn other_violation(_: &PgDatabaseError) -> (u16, String, String) {
-    todo!()
+fn constraint_column<'a>(err: &Violation<'a>) -> Option<&'a str> {
+    let constraint = err.constraint?.strip_suffix("_check")?;
+
+    match err.table {
+        Some(table) => constraint.strip_prefix(table)?.strip_prefix('_'),
+        None => Some(constraint),
+    }
+}
+
+fn pointer(table: Option<&str>, field: Option<String>) -> String {
+    match (table, field) {
+        (Some(table), Some(field)) => format!("/data/{table}/{field}"),
+        (Some(table), None) => format!("/data/{table}"),
+        _ => "/data".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::{parse_detail, violation_error, Violation};
+    use model::error::ErrorCode;
+    use sqlx::{error::ErrorKind, Connection, Executor, PgConnection};
+
+    fn violation(kind: ErrorKind) -> Violation<'static> {
+        Violation {
+            kind,
+            table: None,
+            column: None,
+            constraint: None,
+            detail: None,
+        }
+    }
+
+    #[test]
+    fn unique_violation_should_return_duplicate() {
+        let err = Violation {
+            table: Some("sample"),
+            constraint: Some("sample_name_key"),
+            detail: Some("Key (lower(name::text))=(test) already exists."),
+            ..violation(ErrorKind::UniqueViolation)
+        };
+        let detail = parse_detail(&err, None);
+
+        assert_eq!(detail.code.status(), 409);
+        assert_eq!(detail.code, ErrorCode::Duplicate);
+        assert_eq!(detail.source.pointer.unwrap(), "/data/sample/name");
+    }
+
+    #[test]
+    fn unique_violation_composite_key_should_return_last_column() {
+        let err = Violation {
+            table: Some("sample_translation"),
+            constraint: Some("sample_translation_pkey"),
+            detail: Some("Key (id, language)=(110001, en) already exists."),
+            ..violation(ErrorKind::UniqueViolation)
+        };
+        let detail = parse_detail(&err, None);
+
+        assert_eq!(detail.code.status(), 409);
+        assert_eq!(detail.code, ErrorCode::Duplicate);
+        assert_eq!(
+            detail.source.pointer.unwrap(),
+            "/data/sample_translation/language"
+        );
+    }
+
+    #[test]
+    fn foreign_key_violation_should_return_reference_not_found() {
+        let err = Violation {
+            table: Some("sample_translation"),
+            constraint: Some("sample_translation_id_fkey"),
+            detail: Some(r#"Key (id)=(999999999) is not present in table "sample"."#),
+            ..violation(ErrorKind::ForeignKeyViolation)
+        };
+        let detail = parse_detail(&err, Some("sample_translation"));
+
+        assert_eq!(detail.code.status(), 409);
+        assert_eq!(detail.code, ErrorCode::ReferenceNotFound);
+        assert_eq!(
+            detail.source.pointer.unwrap(),
+            "/data/sample_translation/id"
+        );
+    }
+
+    #[test]
+    fn foreign_key_violation_still_referenced_should_return_entity() {
+        let err = Violation {
+            table: Some("sample_translation"),
+            constraint: Some("sample_translation_id_fkey"),
+            detail: Some(
+                r#"Key (id)=(110001) is still referenced from table "sample_translation"."#,
+            ),
+            ..violation(ErrorKind::ForeignKeyViolation)
+        };
+
+        let detail = parse_detail(&err, Some("sample"));
+        assert_eq!(detail.code.status(), 409);
+        assert_eq!(detail.code, ErrorCode::Referenced);
+        assert_eq!(detail.source.pointer.unwrap(), "/data/sample/id");
+
+        let detail = parse_detail(&err, None);
+        assert_eq!(detail.source.pointer.unwrap(), "/data");
+    }
+
+    #[test]
+    fn not_null_violation_should_return_required() {
+        let err = Violation {
+            table: Some("sample_translation"),
+            column: Some("name"),
+            detail: Some("Failing row contains (118642, null, null, fr, 1)."),
+            ..violation(ErrorKind::NotNullViolation)
+        };
+        let detail = parse_detail(&err, None);
+
+        assert_eq!(detail.code.status(), 400);
+        assert_eq!(detail.code, ErrorCode::Required);
+        assert_eq!(
+            detail.source.pointer.unwrap(),
+            "/data/sample_translation/name"
+        );
+    }
+
+    #[test]
+    fn check_violation_should_return_invalid() {
+        let err = Violation {
+            table: Some("sample"),
+            constraint: Some("sample_amount_check"),
+            detail: Some("Failing row contains (110001, test, null, -1.00, 0, null)."),
+            ..violation(ErrorKind::CheckViolation)
+        };
+        let detail = parse_detail(&err, None);
+
+        assert_eq!(detail.code.status(), 422);
+        assert_eq!(detail.code.as_str(), "invalid");
+        assert_eq!(detail.source.pointer.unwrap(), "/data/sample/amount");
+    }
+
+    #[test]
+    fn check_violation_custom_constraint_should_return_table() {
+        let err = Violation {
+            table: Some("sample"),
+            constraint: Some("positive_amount"),
+            ..violation(ErrorKind::CheckViolation)
+        };
+        let detail = parse_detail(&err, None);
+
+        assert_eq!(detail.code.status(), 422);
+        assert_eq!(detail.code.as_str(), "invalid");
+        assert_eq!(detail.source.pointer.unwrap(), "/data/sample");
+    }
+
+    #[test]
+    fn other_violation_should_return_server_internal() {
+        let err = Violation {
+            table: Some("sample"),
+            ..violation(ErrorKind::Other)
+        };
+        let detail = parse_detail(&err, None);
+
+        assert_eq!(detail.code.status(), 500);
+        assert_eq!(detail.code, ErrorCode::ServerInternal);
+        assert_eq!(detail.source.pointer.unwrap(), "/data/sample");
+    }
+
+    #[test]
+    fn violation_without_table_should_return_data() {
+        let detail = parse_detail(&violation(ErrorKind::UniqueViolation), None);
+
+        assert_eq!(detail.code.status(), 409);
+        assert_eq!(detail.source.pointer.unwrap(), "/data");
+    }
+
+    /// The errors of a real database, from `DATABASE_URL`. Run with
+    /// `cargo test -p database -- --ignored`.
+    #[tokio::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn violation_error_should_read_postgres_errors() {
+        let url = env::var("DATABASE_URL").expect("DATABASE_URL is not set");
+        let mut conn = PgConnection::connect(&url).await.unwrap();
+        let schema = [
+            "create temp table parent (id bigint primary key, name text not null unique, amount numeric check (amount > 0))",
+            "create temp table child (id bigint references parent (id), language text, primary key (id, language))",
+            "insert into parent values (1, 'a', 1)",
+            "insert into child values (1, 'en')",
+        ];
+        for sql in schema {
+            conn.execute(sql).await.unwrap();
+        }
+        let cases = [
+            (
+                "insert into parent values (2, 'a', 1)",
+                409,
+                "duplicate",
+                "/data/parent/name",
+            ),
+            (
+                "insert into child values (1, 'en')",
+                409,
+                "duplicate",
+                "/data/child/language",
+            ),
+            (
+                "insert into child values (2, 'en')",
+                409,
+                "reference_not_found",
+                "/data/child/id",
+            ),
+            (
+                "delete from parent where id = 1",
+                409,
+                "referenced",
+                "/data/parent/id",
+            ),
+            (
+                "insert into parent values (3, null, 1)",
+                400,
+                "required",
+                "/data/parent/name",
+            ),
+            (
+                "insert into parent values (4, 'b', -1)",
+                422,
+                "invalid",
+                "/data/parent/amount",
+            ),
+        ];
+
+        for (sql, status, code, pointer) in cases {
+            let err = conn.execute(sql).await.unwrap_err();
+            let result = violation_error(Some("parent"), err);
+            let detail = &result.errors[0];
+
+            assert_eq!(result.status(), status, "{sql}");
+            assert_eq!(detail.code.as_str(), code, "{sql}");
+            assert_eq!(detail.source.pointer.as_deref(), Some(pointer), "{sql}");
+        }
+    }
 }
@@ -0,0 +1,85 @@
+use model::{
+    error::{idempotency_key_reused, internal_server, ErrorResult},
+    idempotency::{IdempotencyKey, Replay},
+};
+use serde_json::Value;
+use sqlx::{query, query_as, Postgres, Transaction};
+use tracing::{error, Instrument};
+
+use crate::{error_parser::database_error, postgres::query_span};
+
+/// Claims the key of the caller for `scope`, e.g. `sample.create`, within the transaction of the
+/// change. A concurrent request with the same key waits until the transaction ends.
+/// Returns the stored response and its status if the key was already used for the same payload.
+pub async fn claim_key(
+    tx: &mut Transaction<'_, Postgres>,
+    scope: &str,
+    user_id: &str,
+    key: &IdempotencyKey,
+) -> Result<Option<Replay>, ErrorResult> {
+    static CLAIM_SQL: &str = include_str!("sql/idempotency_claim.sql");
+    static GET_SQL: &str = include_str!("sql/idempotency_get.sql");
+
+    let claimed = query(CLAIM_SQL)
+        .bind(scope)
+        .bind(user_id)
+        .bind(&key.key)
+        .bind(&key.fingerprint)
+        .fetch_optional(&mut **tx)
+        .instrument(query_span("idempotency_claim.sql"))
+        .await
+        .map_err(database_error)?;
+
+    if claimed.is_some() {
+        return Ok(None);
+    }
+
+    let (fingerprint, status, response) =
+        query_as::<_, (String, Option<i16>, Option<Value>)>(GET_SQL)
+            .bind(scope)
+            .bind(user_id)
+            .bind(&key.key)
+            .fetch_one(&mut **tx)
+            .instrument(query_span("idempotency_get.sql"))
+            .await
+            .map_err(database_error)?;
+
+    if fingerprint != key.fingerprint {
+        return Err(idempotency_key_reused());
+    }
+
+    match (status, response) {
+        (Some(status), Some(body)) => Ok(Some(Replay {
+            status: status as u16,
+            body,
+        })),
+        _ => {
+            error!(target: "idempotency", "Idempotency key {} has no response.", key.key);
+            Err(internal_server())
+        }
+    }
+}
+
+/// Stores the response of the request that claimed the key and the `status` it is sent with.
+pub async fn save_response(
+    tx: &mut Transaction<'_, Postgres>,
+    scope: &str,
+    user_id: &str,
+    key: &IdempotencyKey,
+    status: u16,
+    response: &Value,
+) -> Result<(), ErrorResult> {
+    static SQL: &str = include_str!("sql/idempotency_save.sql");
+
+    query(SQL)
+        .bind(scope)
+        .bind(user_id)
+        .bind(&key.key)
+        .bind(status as i16)
+        .bind(response)
+        .execute(&mut **tx)
+        .instrument(query_span("idempotency_save.sql"))
+        .await
+        .map(|_| ())
+        .map_err(database_error)
+}
@@ -1,2 +1,3 @@
 pub mod error_parser;
+pub mod idempotency;
 pub mod postgres;
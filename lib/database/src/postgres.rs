@@ -1,23 +1,43 @@
-use std::env;
+use std::{env, error::Error, fmt};
 
 use aws_sdk_secretsmanager::Client;
 use model::error::ErrorResult;
 use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{info_span, Span};
 
 use crate::error_parser::database_error;
 
-pub async fn connect_postgres(client: &Client) -> PgPool {
+/// The pool of connections could not be created, e.g. because `DATABASE_URL` is not set.
+#[derive(Debug)]
+pub struct ConnectError(String);
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for ConnectError {}
+
+impl From<&str> for ConnectError {
+    fn from(message: &str) -> Self {
+        Self(message.to_owned())
+    }
+}
+
+pub async fn connect_postgres(client: &Client) -> Result<PgPool, ConnectError> {
     let url = match env::var_os("DATABASE_URL") {
-        Some(url) => url.into_string().expect("DATABASE_URL is not set"),
-        None => url_from_secret(client).await,
+        Some(url) => url.into_string().map_err(|_| "DATABASE_URL is not valid")?,
+        None => url_from_secret(client).await?,
     };
-
-    PgPool::connect(&url)
+    let pool = PgPool::connect(&url)
         .await
-        .expect("Unable to connect to PostgreSQL")
+        .map_err(|err| ConnectError(format!("Unable to connect to PostgreSQL. {err}")))?;
+
+    Ok(pool)
 }
 
-pub async fn begin(pool: &PgPool) -> Result<Transaction<Postgres>, ErrorResult> {
+pub async fn begin(pool: &PgPool) -> Result<Transaction<'_, Postgres>, ErrorResult> {
     pool.begin().await.map_err(database_error)
 }
 
@@ -29,17 +49,28 @@ pub async fn rollback(tx: Transaction<'_, Postgres>) -> Result<(), ErrorResult>
     tx.rollback().await.map_err(database_error)
 }
 
-async fn url_from_secret(client: &Client) -> String {
-    let prefix = env::var("SST_SSM_PREFIX").expect("SST_SSM_PREFIX is not set");
-    let key = format!("{prefix}Secret/DATABASE_URL/value");
+/// The span of a single query. `file` is the name of the SQL file that is executed.
+pub fn query_span(file: &'static str) -> Span {
+    info_span!(
+        "query",
+        otel.kind = "client",
+        db.system = "postgresql",
+        db.sql.file = file
+    )
+}
 
-    client
+async fn url_from_secret(client: &Client) -> Result<String, ConnectError> {
+    let prefix = env::var("SST_SSM_PREFIX").map_err(|_| "SST_SSM_PREFIX is not set")?;
+    let key = format!("{prefix}Secret/DATABASE_URL/value");
+    let url = client
         .get_secret_value()
         .secret_id(key)
         .send()
         .await
-        .expect("SST_SSM_PREFIX could not be retrieved")
+        .map_err(|err| ConnectError(format!("SST_SSM_PREFIX could not be retrieved. {err}")))?
         .secret_string()
-        .expect("DATABASE_URL is not set from SSM")
-        .to_string()
+        .ok_or("DATABASE_URL is not set from SSM")?
+        .to_string();
+
+    Ok(url)
 }
@@ -0,0 +1,4 @@
+insert into idempotency_key (scope, created_by, key, fingerprint)
+values ($1, $2, $3, $4)
+on conflict do nothing
+returning key
@@ -0,0 +1,3 @@
+select fingerprint, status, response
+from idempotency_key
+where scope = $1 and created_by = $2 and key = $3
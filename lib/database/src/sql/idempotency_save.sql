@@ -0,0 +1,3 @@
+update idempotency_key
+set status = $4, response = $5
+where scope = $1 and created_by = $2 and key = $3
@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+
+use lambda_http::aws_lambda_events::apigw::{
+    ApiGatewayRequestAuthorizer, ApiGatewayRequestAuthorizerJwtDescription,
+};
+use model::error::{forbidden, unauthorized, ErrorCode, ErrorResult};
+use serde_json::Value;
+
+/// The caller of the request, taken from the claims or the identity that an authorizer verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    pub sub: String,
+    pub email: Option<String>,
+    /// The `cognito:groups` the user belongs to, which are used as roles.
+    pub groups: Vec<String>,
+    pub scopes: Vec<String>,
+}
+
+impl Principal {
+    /// API Gateway passes every claim as a string, so lists such as `cognito:groups` come as
+    /// `[admin editor]`. The scopes of access tokens are in the space separated `scope` claim.
+    pub fn from_jwt(jwt: &ApiGatewayRequestAuthorizerJwtDescription) -> Option<Self> {
+        let claims = &jwt.claims;
+        let sub = claims.get("sub").filter(|sub| !sub.is_empty())?.to_owned();
+        let groups = claims
+            .get("cognito:groups")
+            .map(|groups| split(groups.trim_start_matches('[').trim_end_matches(']')))
+            .unwrap_or_default();
+        let scopes = match &jwt.scopes {
+            Some(scopes) if !scopes.is_empty() => scopes.to_owned(),
+            _ => claims.get("scope").map(|s| split(s)).unwrap_or_default(),
+        };
+
+        Some(Self {
+            sub,
+            email: claims.get("email").cloned(),
+            groups,
+            scopes,
+        })
+    }
+
+    /// An HTTP API or Function URL authorizer, which is either a JWT, IAM or Lambda authorizer.
+    pub fn from_authorizer(authorizer: &ApiGatewayRequestAuthorizer) -> Option<Self> {
+        if let Some(jwt) = &authorizer.jwt {
+            return Self::from_jwt(jwt);
+        }
+
+        if let Some(iam) = &authorizer.iam {
+            return Self::from_iam(iam.user_arn.as_deref(), iam.user_id.as_deref());
+        }
+
+        Self::from_lambda(&authorizer.fields)
+    }
+
+    /// The context of a REST API authorizer. A Cognito authorizer passes the token in `claims`,
+    /// a Lambda authorizer passes its own context next to the `principalId`.
+    pub fn from_fields(fields: &HashMap<String, Value>) -> Option<Self> {
+        match fields.get("claims") {
+            Some(Value::Object(claims)) => {
+                Self::from_jwt(&ApiGatewayRequestAuthorizerJwtDescription {
+                    claims: claims
+                        .iter()
+                        .map(|(key, value)| (key.to_owned(), to_claim(value.to_owned())))
+                        .collect(),
+                    scopes: None,
+                })
+            }
+            _ => Self::from_lambda(fields),
+        }
+    }
+
+    /// The context that a Lambda authorizer returned, which an HTTP API passes in
+    /// `authorizer.lambda`. The caller is its `sub`, `principalId` or `userId`, the roles are
+    /// in `cognito:groups` or `groups` and the scopes in `scope` or `scopes`.
+    pub fn from_lambda(context: &HashMap<String, Value>) -> Option<Self> {
+        let mut claims = context
+            .iter()
+            .map(|(key, value)| (key.to_owned(), to_claim(value.to_owned())))
+            .collect::<HashMap<_, _>>();
+
+        for (claim, aliases) in [
+            ("sub", ["principalId", "userId"].as_slice()),
+            ("cognito:groups", &["groups"]),
+            ("scope", &["scopes"]),
+        ] {
+            if claims.get(claim).is_none_or(String::is_empty) {
+                if let Some(value) = aliases.iter().find_map(|alias| claims.get(*alias)) {
+                    claims.insert(claim.to_owned(), value.to_owned());
+                }
+            }
+        }
+
+        Self::from_jwt(&ApiGatewayRequestAuthorizerJwtDescription {
+            claims,
+            scopes: None,
+        })
+    }
+
+    /// Callers that signed the request with IAM are identified by their ARN, or their ID if
+    /// there is no ARN. They have no groups or scopes.
+    pub fn from_iam(user_arn: Option<&str>, user_id: Option<&str>) -> Option<Self> {
+        let sub = user_arn.or(user_id).filter(|sub| !sub.is_empty())?;
+
+        Some(Self {
+            sub: sub.to_owned(),
+            email: None,
+            groups: vec![],
+            scopes: vec![],
+        })
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.groups.iter().any(|group| group == role)
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+/// A claim as API Gateway passes it, which is always a string, with lists as `[first second]`.
+pub(crate) fn to_claim(value: Value) -> String {
+    match value {
+        Value::String(value) => value,
+        Value::Array(values) => {
+            let values = values.into_iter().map(to_claim).collect::<Vec<_>>();
+            format!("[{}]", values.join(" "))
+        }
+        value => value.to_string(),
+    }
+}
+
+fn split(value: &str) -> Vec<String> {
+    value
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|part| !part.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// What the caller needs to be allowed to call a handler, e.g.
+/// `const WRITE: Access = Access::Role("admin");` and then `request.authorize(WRITE)?`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Authenticated,
+    Role(&'static str),
+    Scope(&'static str),
+}
+
+impl Access {
+    pub fn check(self, principal: Option<Principal>) -> Result<Principal, ErrorResult> {
+        let principal = principal.ok_or_else(unauthorized)?;
+        let (key, value, allowed) = match self {
+            Self::Authenticated => return Ok(principal),
+            Self::Role(role) => ("role", role, principal.has_role(role)),
+            Self::Scope(scope) => ("scope", scope, principal.has_scope(scope)),
+        };
+
+        if !allowed {
+            let meta = HashMap::from([(key.to_owned(), Value::from(value))]);
+            return Err(forbidden(meta));
+        }
+
+        Ok(principal)
+    }
+}
+
+/// The `WWW-Authenticate` challenge of an error, as described in RFC 6750.
+pub fn www_authenticate(result: &ErrorResult) -> Option<String> {
+    if result
+        .errors
+        .iter()
+        .any(|error| error.code == ErrorCode::Unauthorized)
+    {
+        return Some("Bearer".to_owned());
+    }
+
+    result
+        .errors
+        .iter()
+        .filter(|error| error.code == ErrorCode::Forbidden)
+        .find_map(|error| error.source.meta.as_ref()?.get("scope")?.as_str())
+        .map(|scope| format!(r#"Bearer error="insufficient_scope", scope="{scope}""#))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use lambda_http::{
+        aws_lambda_events::apigw::{
+            ApiGatewayRequestAuthorizer, ApiGatewayRequestAuthorizerIamDescription,
+            ApiGatewayRequestAuthorizerJwtDescription,
+        },
+        request::RequestContext,
+        RequestExt,
+    };
+    use serde_json::{json, Value};
+
+    use super::{www_authenticate, Access, Principal};
+
+    fn principal(claims: &[(&str, &str)], scopes: Option<Vec<String>>) -> Option<Principal> {
+        let claims = claims
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        Principal::from_jwt(&ApiGatewayRequestAuthorizerJwtDescription { claims, scopes })
+    }
+
+    #[test]
+    fn from_jwt_should_read_groups_and_scopes() {
+        let claims = [
+            ("sub", "user-1"),
+            ("email", "user@example.com"),
+            ("cognito:groups", "[admin editor]"),
+            ("scope", "samples/read samples/write"),
+        ];
+        let principal = principal(&claims, None).unwrap();
+
+        assert_eq!(principal.sub, "user-1");
+        assert_eq!(principal.email.as_deref(), Some("user@example.com"));
+        assert_eq!(principal.groups, vec!["admin", "editor"]);
+        assert_eq!(principal.scopes, vec!["samples/read", "samples/write"]);
+    }
+
+    #[test]
+    fn from_jwt_without_sub_should_return_none() {
+        assert!(principal(&[("email", "user@example.com")], None).is_none());
+    }
+
+    #[test]
+    fn from_fields_should_read_cognito_claims() {
+        let fields = json!({
+            "claims": { "sub": "user-1", "cognito:groups": "admin,editor" },
+            "principalId": "ignored",
+        });
+        let fields = serde_json::from_value(fields).unwrap();
+        let principal = Principal::from_fields(&fields).unwrap();
+
+        assert_eq!(principal.sub, "user-1");
+        assert_eq!(principal.groups, vec!["admin", "editor"]);
+    }
+
+    #[test]
+    fn from_fields_should_read_lambda_context() {
+        let fields = [
+            ("principalId", json!("user-2")),
+            ("cognito:groups", json!(["admin"])),
+        ]
+        .map(|(key, value): (&str, Value)| (key.to_owned(), value))
+        .into();
+        let principal = Principal::from_fields(&fields).unwrap();
+
+        assert_eq!(principal.sub, "user-2");
+        assert!(principal.has_role("admin"));
+    }
+
+    #[test]
+    fn from_authorizer_should_read_lambda_context_of_http_api() {
+        let event = json!({
+            "version": "2.0",
+            "routeKey": "GET /api/admin/samples",
+            "rawPath": "/api/admin/samples",
+            "rawQueryString": "",
+            "headers": { "host": "example.com" },
+            "requestContext": {
+                "accountId": "123456789012",
+                "apiId": "api",
+                "authorizer": {
+                    "lambda": { "userId": "user-3", "groups": "admin,editor", "active": true }
+                },
+                "domainName": "example.com",
+                "http": { "method": "GET", "path": "/api/admin/samples", "protocol": "HTTP/1.1", "sourceIp": "127.0.0.1", "userAgent": "test" },
+                "requestId": "id",
+                "routeKey": "GET /api/admin/samples",
+                "stage": "$default",
+                "time": "12/Mar/2020:19:03:58 +0000",
+                "timeEpoch": 1583348638390u64
+            },
+            "isBase64Encoded": false
+        });
+        let request = lambda_http::request::from_str(&event.to_string()).unwrap();
+        let Some(RequestContext::ApiGatewayV2(context)) = request.request_context_ref() else {
+            panic!("not an HTTP API request");
+        };
+        let principal = Principal::from_authorizer(context.authorizer.as_ref().unwrap()).unwrap();
+
+        assert_eq!(principal.sub, "user-3");
+        assert_eq!(principal.groups, vec!["admin", "editor"]);
+    }
+
+    #[test]
+    fn from_authorizer_should_read_iam_identity() {
+        let authorizer = ApiGatewayRequestAuthorizer {
+            iam: Some(ApiGatewayRequestAuthorizerIamDescription {
+                user_arn: Some("arn:aws:iam::123456789012:user/test".to_owned()),
+                user_id: Some("AIDATEST".to_owned()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let principal = Principal::from_authorizer(&authorizer).unwrap();
+
+        assert_eq!(principal.sub, "arn:aws:iam::123456789012:user/test");
+        assert!(principal.groups.is_empty());
+        assert!(Principal::from_authorizer(&Default::default()).is_none());
+    }
+
+    #[test]
+    fn check_missing_principal_should_return_401() {
+        let result = Access::Authenticated.check(None).unwrap_err();
+
+        assert_eq!(result.status(), 401);
+        assert_eq!(www_authenticate(&result).as_deref(), Some("Bearer"));
+    }
+
+    #[test]
+    fn check_missing_role_should_return_403() {
+        let principal = principal(&[("sub", "user-1")], None);
+        let result = Access::Role("admin").check(principal).unwrap_err();
+
+        assert_eq!(result.status(), 403);
+        assert_eq!(www_authenticate(&result), None);
+    }
+
+    #[test]
+    fn check_missing_scope_should_challenge_scope() {
+        let principal = principal(&[("sub", "user-1")], Some(vec!["samples/read".into()]));
+
+        assert!(Access::Scope("samples/read")
+            .check(principal.clone())
+            .is_ok());
+
+        let result = Access::Scope("samples/write").check(principal).unwrap_err();
+
+        assert_eq!(
+            www_authenticate(&result).as_deref(),
+            Some(r#"Bearer error="insufficient_scope", scope="samples/write""#)
+        );
+    }
+}
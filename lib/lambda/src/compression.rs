@@ -0,0 +1,141 @@
+use std::{
+    env,
+    io::{self, Write},
+};
+
+use flate2::{write::GzEncoder, Compression};
+use lambda_http::{http::header::ACCEPT_ENCODING, Request};
+use once_cell::sync::Lazy;
+
+const THRESHOLD_DEFAULT: usize = 1024;
+
+/// Bodies with at least `COMPRESSION_THRESHOLD` bytes are compressed. Defaults to 1 KiB,
+/// smaller bodies barely get smaller and cost more CPU than they save.
+pub static THRESHOLD: Lazy<usize> = Lazy::new(|| {
+    env::var("COMPRESSION_THRESHOLD")
+        .ok()
+        .and_then(|threshold| threshold.parse().ok())
+        .unwrap_or(THRESHOLD_DEFAULT)
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    /// Picks the encoding from the `Accept-Encoding` header of the request.
+    pub fn negotiate(request: &Request) -> Option<Self> {
+        request
+            .headers()
+            .get(ACCEPT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Self::from_accept_encoding)
+    }
+
+    /// The encoding with the highest quality, Brotli on a tie. A coding that is not listed gets
+    /// the quality of `*`, and `q=0` rules it out. `None` means the body is sent as is.
+    pub fn from_accept_encoding(accept_encoding: &str) -> Option<Self> {
+        let mut brotli = None;
+        let mut gzip = None;
+        let mut any = None;
+
+        for coding in accept_encoding.split(',') {
+            let mut params = coding.split(';').map(str::trim);
+            let name = params.next().unwrap_or_default();
+            let quality = params
+                .filter_map(|param| param.strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if name.eq_ignore_ascii_case("br") {
+                brotli = Some(quality);
+            } else if name.eq_ignore_ascii_case("gzip") || name.eq_ignore_ascii_case("x-gzip") {
+                gzip = Some(quality);
+            } else if name == "*" {
+                any = Some(quality);
+            }
+        }
+
+        let brotli = brotli.or(any).unwrap_or_default();
+        let gzip = gzip.or(any).unwrap_or_default();
+
+        if brotli > 0.0 && brotli >= gzip {
+            Some(Self::Brotli)
+        } else if gzip > 0.0 {
+            Some(Self::Gzip)
+        } else {
+            None
+        }
+    }
+
+    /// The value of the `Content-Encoding` header.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Brotli => "br",
+            Self::Gzip => "gzip",
+        }
+    }
+
+    pub fn compress(&self, body: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Brotli => {
+                let mut compressed = Vec::new();
+                let params = brotli::enc::BrotliEncoderParams {
+                    quality: 5,
+                    ..Default::default()
+                };
+                brotli::BrotliCompress(&mut &body[..], &mut compressed, &params)?;
+
+                Ok(compressed)
+            }
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::Encoding;
+
+    #[test]
+    fn from_accept_encoding_should_prefer_quality_then_brotli() {
+        let negotiate = Encoding::from_accept_encoding;
+
+        assert_eq!(negotiate("gzip, deflate, br"), Some(Encoding::Brotli));
+        assert_eq!(negotiate("br;q=0.5, gzip"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("gzip;q=0.5, *;q=0.8"), Some(Encoding::Brotli));
+        assert_eq!(negotiate("*, br;q=0"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("identity, deflate"), None);
+        assert_eq!(negotiate("br;q=0, gzip;q=0"), None);
+    }
+
+    #[test]
+    fn compress_should_round_trip() {
+        let body = r#"{"name":"sample"}"#.repeat(100);
+
+        let gzip = Encoding::Gzip.compress(body.as_bytes()).unwrap();
+        let mut decoded = String::new();
+        GzDecoder::new(&gzip[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, body);
+
+        let brotli = Encoding::Brotli.compress(body.as_bytes()).unwrap();
+        let mut decoded = String::new();
+        brotli::Decompressor::new(&brotli[..], 4096)
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, body);
+        assert!(brotli.len() < body.len());
+    }
+}
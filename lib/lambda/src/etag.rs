@@ -0,0 +1,115 @@
+use lambda_http::http::header::{HeaderName, ETAG};
+
+use crate::compression::Encoding;
+
+/// The strong entity tag of a version of a record, e.g. `"110001-3"`.
+pub fn entity_tag(id: i64, version: i16) -> String {
+    format!(r#""{id}-{version}""#)
+}
+
+/// The strong entity tag of a representation of a version of a record. The language that was
+/// served and the selected fields change the body, so they are part of the tag, e.g.
+/// `"110001-3-de-id.name"`.
+pub fn representation_tag(
+    id: i64,
+    version: i16,
+    language: Option<&str>,
+    fields: Option<&[String]>,
+) -> String {
+    let mut tag = format!("{id}-{version}");
+
+    if let Some(language) = language {
+        tag.push('-');
+        tag.push_str(language);
+    }
+
+    if let Some(fields) = fields {
+        tag.push('-');
+        tag.push_str(&fields.join("."));
+    }
+
+    format!(r#""{tag}""#)
+}
+
+/// The id and version of an entity tag made by `entity_tag` or `representation_tag`.
+pub fn parse_entity_tag(tag: &str) -> Option<(i64, i16)> {
+    let mut parts = tag
+        .trim()
+        .strip_prefix('"')?
+        .strip_suffix('"')?
+        .splitn(3, '-');
+    let id = parts.next()?.parse().ok()?;
+    let version = parts.next()?.parse().ok()?;
+
+    Some((id, version))
+}
+
+/// The tag of the body compressed with the content `coding`, e.g. `"1-2-br"` for `"1-2"`.
+pub fn encoded_tag(etag: &str, coding: &str) -> String {
+    match etag.strip_suffix('"') {
+        Some(tag) => format!(r#"{tag}-{coding}""#),
+        None => etag.to_owned(),
+    }
+}
+
+/// Whether one of the tags of an `If-None-Match` header matches `etag`. The comparison is weak,
+/// so `W/"1-2"` matches `"1-2"`, and so is the tag of a compressed body, e.g. `"1-2-br"`.
+pub fn none_match(header: &str, etag: &str) -> bool {
+    let tags =
+        [Encoding::Brotli, Encoding::Gzip].map(|encoding| encoded_tag(etag, encoding.name()));
+
+    header
+        .split(',')
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag || tags.iter().any(|encoded| encoded == tag))
+}
+
+/// A response body with the `ETag` of the record, e.g. `Tagged::new(sample, etag)`.
+/// Any other body can be tagged, such as a `Localized` one.
+pub struct Tagged<T> {
+    pub value: T,
+    pub etag: String,
+}
+
+impl<T> Tagged<T> {
+    pub fn new(value: T, etag: String) -> Self {
+        Self { value, etag }
+    }
+
+    pub fn header(&self) -> (HeaderName, String) {
+        (ETAG, self.etag.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encoded_tag, entity_tag, none_match, parse_entity_tag, representation_tag};
+
+    #[test]
+    fn parse_entity_tag_should_return_id_and_version() {
+        let fields = ["id".to_owned(), "name".to_owned()];
+        let tag = representation_tag(110001, 3, Some("de-ch"), Some(&fields));
+
+        assert_eq!(entity_tag(110001, 3), r#""110001-3""#);
+        assert_eq!(tag, r#""110001-3-de-ch-id.name""#);
+        assert_eq!(parse_entity_tag(r#" "110001-3" "#), Some((110001, 3)));
+        assert_eq!(parse_entity_tag(&tag), Some((110001, 3)));
+        assert_eq!(
+            parse_entity_tag(&encoded_tag(&tag, "br")),
+            Some((110001, 3))
+        );
+        assert_eq!(parse_entity_tag("110001-3"), None);
+        assert_eq!(parse_entity_tag(r#"W/"110001-3""#), None);
+        assert_eq!(parse_entity_tag(r#""abc""#), None);
+    }
+
+    #[test]
+    fn none_match_should_compare_weakly() {
+        let etag = entity_tag(1, 2);
+
+        assert!(none_match(r#""1-1", W/"1-2""#, &etag));
+        assert!(none_match(r#""1-2-gzip""#, &etag));
+        assert!(none_match("*", &etag));
+        assert!(!none_match(r#""1-1""#, &etag));
+    }
+}
@@ -0,0 +1,231 @@
+use std::env;
+
+use lambda_http::{http::header::ACCEPT, Request};
+use model::error::{export_too_large, internal_server, ErrorResult};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tracing::error;
+
+use crate::problem::CONTENT_TYPE_JSON;
+
+pub const CONTENT_TYPE_CSV: &str = "text/csv";
+pub const CONTENT_TYPE_NDJSON: &str = "application/x-ndjson";
+
+const SIZE_LIMIT_DEFAULT: usize = 5 * 1024 * 1024;
+
+/// Exports above `EXPORT_SIZE_LIMIT` bytes are a `413`. Defaults to 5 MiB, which keeps the
+/// response within the 6 MB limit of Lambda.
+pub static SIZE_LIMIT: Lazy<usize> = Lazy::new(|| {
+    env::var("EXPORT_SIZE_LIMIT")
+        .ok()
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(SIZE_LIMIT_DEFAULT)
+});
+
+/// The `EXPORT_URL` that streams an export without a limit, which the `413` points to.
+static EXPORT_URL: Lazy<Option<String>> = Lazy::new(|| env::var("EXPORT_URL").ok());
+
+/// A format to export a list in instead of a JSON page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One row per record with a header row of the columns.
+    Csv,
+    /// One JSON object per line.
+    Ndjson,
+}
+
+impl ExportFormat {
+    /// Picks the format from the `Accept` header of the request.
+    pub fn negotiate(request: &Request) -> Option<Self> {
+        request
+            .headers()
+            .get(ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Self::from_accept)
+    }
+
+    /// The export format with the highest quality. Only explicitly listed media types count and
+    /// JSON wins ties, so wildcards and `application/json` keep the JSON page.
+    pub fn from_accept(accept: &str) -> Option<Self> {
+        let mut json = 0.0;
+        let mut csv = 0.0;
+        let mut ndjson = 0.0;
+
+        for range in accept.split(',') {
+            let mut parts = range.split(';').map(str::trim);
+            let media_type = parts.next().unwrap_or_default();
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if media_type.eq_ignore_ascii_case(CONTENT_TYPE_JSON) {
+                json = quality;
+            } else if media_type.eq_ignore_ascii_case(CONTENT_TYPE_CSV) {
+                csv = quality;
+            } else if media_type.eq_ignore_ascii_case(CONTENT_TYPE_NDJSON) {
+                ndjson = quality;
+            }
+        }
+
+        if csv > json && csv >= ndjson {
+            Some(Self::Csv)
+        } else if ndjson > json && ndjson > csv {
+            Some(Self::Ndjson)
+        } else {
+            None
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Ndjson => CONTENT_TYPE_NDJSON,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// The body of an export, written a record at a time up to its size limit.
+pub struct Export {
+    pub format: ExportFormat,
+    pub body: String,
+    limit: usize,
+}
+
+impl Export {
+    /// A CSV export starts with the header row of the `columns`.
+    pub fn new(format: ExportFormat, columns: &[&str]) -> Self {
+        Self::with_limit(format, columns, *SIZE_LIMIT)
+    }
+
+    pub fn with_limit(format: ExportFormat, columns: &[&str], limit: usize) -> Self {
+        let mut export = Self {
+            format,
+            body: String::new(),
+            limit,
+        };
+
+        if format == ExportFormat::Csv {
+            export.body = csv_row(columns);
+        }
+
+        export
+    }
+
+    /// Writes a CSV row. The values must be in the order of the columns.
+    pub fn push_row<S: AsRef<str>>(&mut self, values: &[S]) -> Result<(), ErrorResult> {
+        self.push(&csv_row(values))
+    }
+
+    /// Writes an NDJSON line. The members are in the order of the fields of the struct.
+    pub fn push_json<T: Serialize>(&mut self, value: &T) -> Result<(), ErrorResult> {
+        let mut line = serde_json::to_string(value).map_err(|error| {
+            error!(target: "export", "Error when parsing the struct. {:?}", error);
+            internal_server()
+        })?;
+        line.push('\n');
+
+        self.push(&line)
+    }
+
+    /// An export that would grow above the limit is a `413`, so the caller stops reading.
+    fn push(&mut self, text: &str) -> Result<(), ErrorResult> {
+        if self.body.len() + text.len() > self.limit {
+            return Err(export_too_large(self.limit, EXPORT_URL.as_deref()));
+        }
+
+        self.body.push_str(text);
+
+        Ok(())
+    }
+}
+
+fn csv_row<S: AsRef<str>>(values: &[S]) -> String {
+    let mut row = values
+        .iter()
+        .map(|value| csv_value(value.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    row.push_str("\r\n");
+
+    row
+}
+
+/// A value of a CSV row as described in RFC 4180. Text that a spreadsheet would run as a
+/// formula, e.g. `=1+1`, is prefixed with `'`.
+fn csv_value(value: &str) -> String {
+    let value =
+        if value.starts_with(['=', '+', '-', '@', '\t', '\r']) && value.parse::<f64>().is_err() {
+            format!("'{value}")
+        } else {
+            value.to_owned()
+        };
+
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Export, ExportFormat};
+
+    #[test]
+    fn from_accept_should_keep_json_on_tie_and_wildcard() {
+        assert_eq!(
+            ExportFormat::from_accept("text/csv"),
+            Some(ExportFormat::Csv)
+        );
+        assert_eq!(
+            ExportFormat::from_accept("application/json;q=0.5, application/x-ndjson"),
+            Some(ExportFormat::Ndjson)
+        );
+        assert_eq!(
+            ExportFormat::from_accept("text/csv, application/json"),
+            None
+        );
+        assert_eq!(ExportFormat::from_accept("*/*"), None);
+    }
+
+    #[test]
+    fn push_row_should_quote_and_escape() {
+        let mut export = Export::new(ExportFormat::Csv, &["name", "amount"]);
+        export.push_row(&["a, \"b\"", "-1.5"]).unwrap();
+        export.push_row(&["=SUM(A1)", "2"]).unwrap();
+
+        assert_eq!(
+            export.body,
+            "name,amount\r\n\"a, \"\"b\"\"\",-1.5\r\n'=SUM(A1),2\r\n"
+        );
+    }
+
+    #[test]
+    fn push_json_should_write_lines() {
+        let mut export = Export::new(ExportFormat::Ndjson, &["name"]);
+        export.push_json(&json!({ "name": "a" })).unwrap();
+        export.push_json(&json!({ "name": "b" })).unwrap();
+
+        assert_eq!(export.body, "{\"name\":\"a\"}\n{\"name\":\"b\"}\n");
+    }
+
+    #[test]
+    fn push_above_limit_should_return_413() {
+        let mut export = Export::with_limit(ExportFormat::Csv, &["name"], 11);
+        export.push_row(&["a"]).unwrap();
+        let result = export.push_row(&["b"]).unwrap_err();
+
+        assert_eq!(result.status(), 413);
+        assert_eq!(export.body, "name\r\na\r\n");
+    }
+}
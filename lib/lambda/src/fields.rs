@@ -0,0 +1,170 @@
+use model::error::{internal_server, invalid_parameter, ErrorResult};
+use serde::{
+    de::{self, Visitor},
+    forward_to_deserialize_any, Deserialize, Deserializer, Serialize,
+};
+use serde_json::Value;
+use tracing::error;
+
+pub const FIELDS: &str = "fields";
+
+/// The field names of a `fields` query parameter, e.g. `id,name,amount`. A name that is not one
+/// of the `names` of the resource is an `invalid_parameter`.
+pub fn parse_fields(value: &str, names: &[&str]) -> Result<Vec<String>, ErrorResult> {
+    let fields = value
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+
+    if fields.is_empty() || fields.iter().any(|field| !names.contains(&field.as_str())) {
+        return Err(invalid_parameter(FIELDS.to_owned()));
+    }
+
+    Ok(fields)
+}
+
+/// The names of the fields of the resource `T`, as they are serialized. Only the deserializer of
+/// a struct is told its fields without a value, so a resource that can be selected from derives
+/// `Deserialize` too. The names of a struct that is not one, e.g. a map, are empty.
+pub fn field_names<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    let mut names = &[][..];
+    let _ = T::deserialize(FieldNames(&mut names));
+
+    names
+}
+
+/// The JSON of `value` with only the `fields` of the resource. The resources of a list are the
+/// items of its `data`, so the other members of a `Page` or `Seek` are kept.
+pub fn select_fields<T: Serialize>(value: &T, fields: &[String]) -> Result<Value, ErrorResult> {
+    let mut json = serde_json::to_value(value).map_err(|error| {
+        error!(target: "select_fields", "Error when parsing the struct. {:?}", error);
+        internal_server()
+    })?;
+
+    let resources = match &mut json {
+        Value::Object(object) if object.get("data").is_some_and(Value::is_array) => {
+            object.get_mut("data").and_then(Value::as_array_mut)
+        }
+        Value::Array(array) => Some(array),
+        _ => None,
+    };
+
+    match resources {
+        Some(resources) => resources
+            .iter_mut()
+            .for_each(|resource| retain(resource, fields)),
+        None => retain(&mut json, fields),
+    }
+
+    Ok(json)
+}
+
+fn retain(resource: &mut Value, fields: &[String]) {
+    if let Value::Object(object) = resource {
+        object.retain(|key, _| fields.contains(key));
+    }
+}
+
+/// A deserializer that only reads the field names of a struct and then gives up.
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de, 'a> Deserializer<'de> for FieldNames<'a> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        _: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("only the field names are read"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::{field_names, parse_fields, select_fields};
+
+    #[derive(Serialize)]
+    struct Page<T> {
+        data: Vec<T>,
+        total: i64,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Sample {
+        id: i64,
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+    }
+
+    fn sample() -> Sample {
+        Sample {
+            id: 1,
+            name: "a".to_owned(),
+            description: None,
+        }
+    }
+
+    fn fields(value: &str) -> Vec<String> {
+        parse_fields(value, field_names::<Sample>()).unwrap()
+    }
+
+    #[test]
+    fn field_names_should_read_serialized_names() {
+        assert_eq!(field_names::<Sample>(), ["id", "name", "description"]);
+        assert!(field_names::<Vec<Sample>>().is_empty());
+    }
+
+    #[test]
+    fn select_fields_should_prune_resource() {
+        assert_eq!(
+            select_fields(&sample(), &fields("id, name")).unwrap(),
+            json!({ "id": 1, "name": "a" })
+        );
+        assert_eq!(
+            select_fields(&sample(), &fields("description")).unwrap(),
+            json!({})
+        );
+    }
+
+    #[test]
+    fn select_fields_should_prune_data_of_list() {
+        let page = Page {
+            data: vec![sample()],
+            total: 1,
+        };
+
+        assert_eq!(
+            select_fields(&page, &fields("name")).unwrap(),
+            json!({ "data": [{ "name": "a" }], "total": 1 })
+        );
+    }
+
+    #[test]
+    fn parse_fields_unknown_should_return_invalid_parameter() {
+        let names = field_names::<Sample>();
+
+        assert!(parse_fields("id,total", names).is_err());
+        assert!(parse_fields("Name", names).is_err());
+        assert!(parse_fields(" , ", names).is_err());
+    }
+}
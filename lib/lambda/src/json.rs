@@ -1,31 +1,206 @@
 use std::future::Future;
 
-use lambda_http::{http::header::CONTENT_TYPE, Body, Error, Response};
+use lambda_http::{
+    http::header::{
+        HeaderName, CONTENT_DISPOSITION, CONTENT_ENCODING, CONTENT_TYPE, ETAG, VARY,
+        WWW_AUTHENTICATE,
+    },
+    Body, Error, Request, Response,
+};
 use model::error::{internal_server, ErrorResult};
 use serde::Serialize;
-use tracing::error;
+use tracing::{error, Instrument, Span};
 
-pub async fn json_handler<T, F>(handler: F) -> Result<Response<Body>, Error>
+use crate::{
+    auth::www_authenticate,
+    compression::{Encoding, THRESHOLD},
+    etag::{encoded_tag, Tagged},
+    fields::select_fields,
+    jwt::verify_alb,
+    language::Localized,
+    problem::{ErrorFormat, Problem, CONTENT_TYPE_JSON},
+    request::{RequestExtension, X_REQUEST_ID},
+    response::{ExportResponse, IntoResponse, JsonResponse, Negotiated},
+    telemetry::flush,
+    tracing::request_span,
+};
+
+/// What is needed from the request to build the response after the request
+/// has been moved into the handler.
+pub(crate) struct ResponseContext {
+    pub(crate) request_id: Option<String>,
+    format: ErrorFormat,
+    instance: String,
+    languages: Vec<String>,
+    encoding: Option<Encoding>,
+}
+
+impl ResponseContext {
+    pub(crate) fn new(request: &Request) -> Self {
+        Self {
+            request_id: request.get_request_id(),
+            format: ErrorFormat::negotiate(request),
+            instance: request.uri().path().to_owned(),
+            languages: request.get_languages(),
+            encoding: Encoding::negotiate(request),
+        }
+    }
+}
+
+/// The body of a successful response. Every `Serialize` value is one, `Localized` also sets
+/// the `Content-Language` header and `Tagged` the `ETag` header.
+pub trait JsonBody {
+    type Value: Serialize;
+
+    fn into_parts(self) -> (Self::Value, Vec<(HeaderName, String)>);
+}
+
+impl<T: Serialize> JsonBody for T {
+    type Value = T;
+
+    fn into_parts(self) -> (Self::Value, Vec<(HeaderName, String)>) {
+        (self, Vec::new())
+    }
+}
+
+impl<T: JsonBody> JsonBody for Tagged<T> {
+    type Value = T::Value;
+
+    fn into_parts(self) -> (Self::Value, Vec<(HeaderName, String)>) {
+        let header = self.header();
+        let (value, mut headers) = self.value.into_parts();
+        headers.push(header);
+
+        (value, headers)
+    }
+}
+
+impl<T: Serialize> JsonBody for Localized<T> {
+    type Value = T;
+
+    fn into_parts(self) -> (Self::Value, Vec<(HeaderName, String)>) {
+        let headers = self.headers();
+
+        (self.value, headers)
+    }
+}
+
+/// Runs the handler with the request and serializes the body of the result as JSON, or sends
+/// the export of a `Negotiated` result as is. Errors are rendered in the format negotiated from the request's `Accept` header.
+/// Everything logged during the invocation is within a span that carries the request id.
+pub async fn json_handler<R, H, F>(request: Request, handler: H) -> Result<Response<Body>, Error>
 where
-    T: Serialize,
-    F: Future<Output = Result<(u16, T), ErrorResult>>,
+    R: IntoResponse,
+    H: FnOnce(Request) -> F,
+    F: Future<Output = Result<R, ErrorResult>>,
 {
-    match handler.await {
-        Ok((status, value)) => json_response(status, value),
-        Err(error) => error_response(error),
+    let context = ResponseContext::new(&request);
+    let span = request_span(&request, context.request_id.as_deref());
+    let response = async move {
+        let response = match handler(verify_alb(request).await).await {
+            Ok(response) => match response.into_response() {
+                Negotiated::Json(response) => json_response(response, &context),
+                Negotiated::Export(response) => export_response(response, &context),
+            },
+            Err(error) => error_response(error, &context),
+        };
+
+        if let Ok(response) = &response {
+            Span::current().record("http.response.status_code", response.status().as_u16());
+        }
+
+        response
     }
+    .instrument(span)
+    .await;
+
+    flush().await;
+
+    response
 }
 
-fn json_response<T: Serialize>(status: u16, value: T) -> Result<Response<Body>, Error> {
-    to_json(&value, "json_response")
-        .map(|json| build_response(status, json))
-        .unwrap_or_else(error_response)
+fn json_response<T: JsonBody>(
+    response: JsonResponse<T>,
+    context: &ResponseContext,
+) -> Result<Response<Body>, Error> {
+    let JsonResponse {
+        status,
+        mut headers,
+        body,
+        fields,
+    } = response;
+
+    let Some((value, body_headers)) = body.map(JsonBody::into_parts) else {
+        return build_response(status, String::new(), CONTENT_TYPE_JSON, headers, context);
+    };
+    headers.extend(body_headers);
+
+    // A `204` has no body. A `304` has none either, but the body of its `200` decides its coding.
+    if status == 204 {
+        return build_response(status, String::new(), CONTENT_TYPE_JSON, headers, context);
+    }
+
+    let json = match &fields {
+        Some(fields) => {
+            select_fields(&value, fields).and_then(|value| to_json(&value, "json_response"))
+        }
+        None => to_json(&value, "json_response"),
+    };
+
+    json.map(|json| build_response(status, json, CONTENT_TYPE_JSON, headers, context))
+        .unwrap_or_else(|error| error_response(error, context))
 }
 
-fn error_response(result: ErrorResult) -> Result<Response<Body>, Error> {
-    to_json(&result, "error_response")
-        .map(|json| build_response(result.status, json))
-        .unwrap_or_else(error_response)
+fn export_response(
+    response: ExportResponse,
+    context: &ResponseContext,
+) -> Result<Response<Body>, Error> {
+    let ExportResponse {
+        export,
+        name,
+        mut headers,
+    } = response;
+    let format = export.format;
+    let disposition = format!("attachment; filename=\"{name}.{}\"", format.extension());
+    headers.push((CONTENT_DISPOSITION, disposition));
+
+    build_response(200, export.body, format.content_type(), headers, context)
+}
+
+pub(crate) fn error_response(
+    mut result: ErrorResult,
+    context: &ResponseContext,
+) -> Result<Response<Body>, Error> {
+    result.localize(&context.languages);
+
+    if result.status() >= 500 {
+        result.request_id.clone_from(&context.request_id);
+    }
+
+    let format = context.format;
+    let json = match format {
+        ErrorFormat::Json => to_json(&result, "error_response"),
+        ErrorFormat::Problem => {
+            let problem = Problem::new(&result, Some(&context.instance));
+            to_json(&problem, "error_response")
+        }
+    };
+
+    let headers = www_authenticate(&result)
+        .map(|challenge| (WWW_AUTHENTICATE, challenge))
+        .into_iter()
+        .collect();
+
+    json.map(|json| {
+        build_response(
+            result.status(),
+            json,
+            format.content_type(),
+            headers,
+            context,
+        )
+    })
+    .unwrap_or_else(|error| error_response(error, context))
 }
 
 fn to_json<T: Serialize>(value: &T, target: &str) -> Result<String, ErrorResult> {
@@ -35,10 +210,96 @@ fn to_json<T: Serialize>(value: &T, target: &str) -> Result<String, ErrorResult>
     })
 }
 
-fn build_response(status: u16, json: String) -> Result<Response<Body>, Error> {
-    Response::builder()
-        .header(CONTENT_TYPE, "application/json")
-        .status(status)
-        .body(json.into())
-        .map(Ok)?
+/// A `304` is built from the `json` of the `200` it stands for, so that it has the same `ETag`
+/// and `Vary` as that `200`, but it is sent without the body.
+fn build_response(
+    status: u16,
+    json: String,
+    content_type: &str,
+    headers: Vec<(HeaderName, String)>,
+    context: &ResponseContext,
+) -> Result<Response<Body>, Error> {
+    let mut builder = Response::builder().status(status);
+    let not_modified = status == 304;
+    let large = !json.is_empty() && json.len() >= *THRESHOLD;
+    let encoding = context.encoding.filter(|_| large);
+    let compressed = encoding.filter(|_| !not_modified).and_then(|encoding| {
+        encoding
+            .compress(json.as_bytes())
+            .map(|compressed| (encoding, compressed))
+            .inspect_err(|error| error!(target: "build_response", "Error when compressing the body. {:?}", error))
+            .ok()
+    });
+    let coding = match &compressed {
+        Some((encoding, _)) => Some(*encoding),
+        None => encoding.filter(|_| not_modified),
+    };
+
+    if !json.is_empty() && !not_modified {
+        builder = builder.header(CONTENT_TYPE, content_type);
+    }
+
+    for (name, value) in headers {
+        // The compressed bytes are another representation, so they need another strong tag.
+        let value = match coding {
+            Some(encoding) if name == ETAG => encoded_tag(&value, encoding.name()),
+            _ => value,
+        };
+        builder = builder.header(name, value);
+    }
+
+    if let Some(request_id) = &context.request_id {
+        builder = builder.header(X_REQUEST_ID, request_id);
+    }
+
+    if large {
+        // Whether the body is compressed depends on `Accept-Encoding`, so caches must key on it.
+        builder = builder.header(VARY, "accept-encoding");
+    }
+
+    if json.is_empty() || not_modified {
+        return builder.body(Body::Empty).map(Ok)?;
+    }
+
+    match compressed {
+        Some((encoding, compressed)) => builder
+            .header(CONTENT_ENCODING, encoding.name())
+            .body(Body::Binary(compressed))
+            .map(Ok)?,
+        None => builder.body(json.into()).map(Ok)?,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lambda_http::{
+        http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, ETAG, VARY},
+        Request,
+    };
+    use serde_json::json;
+
+    use super::{json_response, ResponseContext};
+    use crate::{etag::Tagged, response::JsonResponse};
+
+    #[test]
+    fn json_response_not_modified_should_have_tag_and_vary_of_ok() {
+        let mut request = Request::default();
+        request
+            .headers_mut()
+            .insert(ACCEPT_ENCODING, "gzip".parse().unwrap());
+        let context = ResponseContext::new(&request);
+        let response = |status| {
+            let body = Tagged::new(json!({ "name": "a".repeat(2048) }), "\"1-2\"".to_owned());
+            json_response(JsonResponse::new(status, body), &context).unwrap()
+        };
+        let ok = response(200);
+        let not_modified = response(304);
+
+        assert_eq!(ok.headers()[ETAG], "\"1-2-gzip\"");
+        assert_eq!(ok.headers()[CONTENT_ENCODING], "gzip");
+        assert_eq!(not_modified.headers()[ETAG], ok.headers()[ETAG]);
+        assert_eq!(not_modified.headers()[VARY], "accept-encoding");
+        assert!(not_modified.headers().get(CONTENT_ENCODING).is_none());
+        assert!(not_modified.body().is_empty());
+    }
 }
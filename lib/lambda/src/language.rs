@@ -0,0 +1,137 @@
+use std::env;
+
+use lambda_http::http::header::{HeaderName, CONTENT_LANGUAGE, VARY};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+/// The language of the stage, `LANGUAGE_DEFAULT`, is always the last one to fall back to.
+static LANGUAGE_DEFAULT: Lazy<String> = Lazy::new(|| {
+    env::var("LANGUAGE_DEFAULT")
+        .map(|language| language.to_lowercase())
+        .unwrap_or_else(|_| "en".to_owned())
+});
+
+/// The language ranges of an `Accept-Language` header from the most to the least preferred,
+/// e.g. `de-CH, en;q=0.8, de;q=0.9` is `de-CH`, `de`, `en`. Ranges of the same quality keep
+/// their order. `*` and ranges with `q=0` are left out.
+pub fn parse_accept_language(value: &str) -> Vec<String> {
+    let mut ranges = value
+        .split(',')
+        .filter_map(|range| {
+            let mut params = range.split(';').map(str::trim);
+            let tag = params.next().filter(|tag| !tag.is_empty() && *tag != "*")?;
+            let quality = params
+                .filter_map(|param| param.strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            (quality > 0.0).then(|| (tag.to_owned(), quality))
+        })
+        .collect::<Vec<_>>();
+    ranges.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+    ranges.into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// Every tag followed by its less specific tags and then the default language, e.g. `de-CH`
+/// is `de-ch`, `de`, `en`. The tags are in lowercase and are not repeated.
+pub fn fallback_chain(tags: &[String]) -> Vec<String> {
+    let mut chain = Vec::<String>::with_capacity(tags.len() * 2 + 1);
+    let tags = tags
+        .iter()
+        .flat_map(|tag| {
+            let tag = tag.to_lowercase();
+            let parts = tag.split('-').collect::<Vec<_>>();
+
+            (1..=parts.len())
+                .rev()
+                .map(|len| parts[..len].join("-"))
+                .collect::<Vec<_>>()
+        })
+        .chain([LANGUAGE_DEFAULT.to_owned()]);
+
+    for tag in tags {
+        if !chain.contains(&tag) {
+            chain.push(tag);
+        }
+    }
+
+    chain
+}
+
+/// A response body that was translated to one or more languages. The languages are sent in
+/// the `Content-Language` header.
+pub struct Localized<T> {
+    pub value: T,
+    pub languages: Vec<String>,
+}
+
+impl<T: Serialize> Localized<T> {
+    /// `languages` are the languages of the translations that were served, one per record.
+    pub fn new(value: T, languages: impl IntoIterator<Item = String>) -> Self {
+        let mut served = Vec::<String>::new();
+
+        for language in languages {
+            if !served.contains(&language) {
+                served.push(language);
+            }
+        }
+
+        Self {
+            value,
+            languages: served,
+        }
+    }
+
+    /// The `Content-Language` of the served languages and `Vary: accept-language`, because
+    /// another `Accept-Language` may be served another translation.
+    pub fn headers(&self) -> Vec<(HeaderName, String)> {
+        let mut headers = vec![(VARY, "accept-language".to_owned())];
+
+        if !self.languages.is_empty() {
+            headers.push((CONTENT_LANGUAGE, self.languages.join(", ")));
+        }
+
+        headers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lambda_http::http::header::{CONTENT_LANGUAGE, VARY};
+
+    use super::{fallback_chain, parse_accept_language, Localized};
+
+    #[test]
+    fn parse_accept_language_should_order_by_quality() {
+        let ranges = parse_accept_language("de-CH, en;q=0.8, de;q=0.9, *;q=0.5, fr;q=0");
+
+        assert_eq!(ranges, vec!["de-CH", "de", "en"]);
+    }
+
+    #[test]
+    fn parse_accept_language_invalid_quality_should_default_to_1() {
+        let ranges = parse_accept_language("en;q=0.5, de;q=abc");
+
+        assert_eq!(ranges, vec!["de", "en"]);
+    }
+
+    #[test]
+    fn fallback_chain_should_add_primary_and_default() {
+        let tags = ["de-CH".to_owned(), "fr".to_owned(), "de".to_owned()];
+
+        assert_eq!(fallback_chain(&tags), vec!["de-ch", "de", "fr", "en"]);
+        assert_eq!(fallback_chain(&[]), vec!["en"]);
+    }
+
+    #[test]
+    fn localized_should_list_served_languages() {
+        let languages = ["de", "en", "de"].map(str::to_owned);
+        let localized = Localized::new((), languages);
+        let headers = localized.headers();
+
+        assert_eq!(headers[0], (VARY, "accept-language".to_owned()));
+        assert_eq!(headers[1], (CONTENT_LANGUAGE, "de, en".to_owned()));
+        assert_eq!(Localized::new((), None).headers().len(), 1);
+    }
+}
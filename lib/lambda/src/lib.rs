@@ -1,5 +1,20 @@
+pub mod auth;
+pub mod compression;
+pub mod etag;
+pub mod export;
+pub mod fields;
 pub mod json;
+pub mod jwt;
+pub mod language;
 pub mod page;
+pub mod patch;
+pub mod payload;
+pub mod problem;
 pub mod request;
+pub mod response;
+pub mod router;
 pub mod seek;
+pub mod serve;
+pub mod stream;
+pub mod telemetry;
 pub mod tracing;
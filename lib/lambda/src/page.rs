@@ -1,41 +1,58 @@
-use model::page::PageRequest;
+use model::{
+    error::{invalid_parameter, ErrorResult},
+    limit::SizeLimit,
+    page::PageRequest,
+};
 
 use lambda_http::Request;
+use serde::Deserialize;
+use validator::Validate;
 
 use crate::request::RequestExtension;
 
 const PAGE_DEFAULT: i64 = 1;
 const PAGE_MIN: i64 = 1;
-const SIZE_DEFAULT: i16 = 20;
-const SIZE_MIN: i16 = 1;
 
-pub trait ApiPageRequest {
-    fn read(request: &Request) -> PageRequest;
+#[derive(Deserialize, Validate)]
+struct PageQuery {
+    page: Option<i64>,
+    size: Option<i16>,
+}
+
+pub trait ApiPageRequest: Sized {
+    fn read(request: &Request) -> Result<Self, ErrorResult> {
+        Self::read_with(request, SizeLimit::DEFAULT)
+    }
+
+    /// Same as `read` with the page sizes of the endpoint.
+    fn read_with(request: &Request, size_limit: SizeLimit) -> Result<Self, ErrorResult>;
 }
 
 impl ApiPageRequest for PageRequest {
-    fn read(request: &Request) -> PageRequest {
-        let page = request
-            .query_param("page")
-            .unwrap_or(PAGE_DEFAULT)
-            .max(PAGE_MIN);
-        let size = request
-            .query_param("size")
-            .unwrap_or(SIZE_DEFAULT)
-            .max(SIZE_MIN);
-        let offset = ((page - 1) * i64::from(size)).max(0);
-
-        PageRequest { page, size, offset }
+    fn read_with(request: &Request, size_limit: SizeLimit) -> Result<Self, ErrorResult> {
+        let query = request.validate_query::<PageQuery>()?;
+        let page = query.page.unwrap_or(PAGE_DEFAULT).max(PAGE_MIN);
+        let size = size_limit.size(query.size)?;
+        let offset = (page - 1)
+            .checked_mul(i64::from(size))
+            .ok_or_else(|| invalid_parameter("page".to_owned()))?;
+
+        Ok(PageRequest {
+            page,
+            size,
+            offset,
+            size_limit,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::page::{PAGE_MIN, SIZE_MIN};
+    use crate::page::PAGE_MIN;
 
     use super::ApiPageRequest;
     use lambda_http::{aws_lambda_events::query_map::QueryMap, Request, RequestExt};
-    use model::page::PageRequest;
+    use model::{limit::SizeLimit, page::PageRequest};
     use std::collections::HashMap;
 
     #[test]
@@ -46,7 +63,7 @@ mod tests {
         ]);
         let query_map = QueryMap::from(query_params);
         let request = Request::default().with_query_string_parameters(query_map);
-        let result = PageRequest::read(&request);
+        let result = PageRequest::read(&request).unwrap();
 
         assert_eq!(result.page, 1);
         assert_eq!(result.size, 10);
@@ -62,7 +79,7 @@ mod tests {
         let query_map = QueryMap::from(query_params);
 
         let request = Request::default().with_query_string_parameters(query_map);
-        let result = PageRequest::read(&request);
+        let result = PageRequest::read(&request).unwrap();
 
         assert_eq!(result.page, PAGE_MIN);
         assert_eq!(result.size, 10);
@@ -70,19 +87,28 @@ mod tests {
     }
 
     #[test]
-    fn read_negative_size_should_return_min() {
-        let query_params = HashMap::<String, Vec<String>>::from([
-            ("page".into(), vec!["1".into()]),
-            ("size".into(), vec!["-1".into()]),
-        ]);
-        let query_map = QueryMap::from(query_params);
+    fn read_size_out_of_range_should_return_parameter() {
+        for size in ["-1", "101"] {
+            let query_params =
+                HashMap::<String, Vec<String>>::from([("size".into(), vec![size.into()])]);
+            let query_map = QueryMap::from(query_params);
+
+            let request = Request::default().with_query_string_parameters(query_map);
+            let result = PageRequest::read(&request).err().unwrap();
+
+            assert_eq!(result.status(), 400);
+            assert_eq!(result.errors[0].source.parameter.as_deref(), Some("size"));
+        }
+    }
 
-        let request = Request::default().with_query_string_parameters(query_map);
-        let result = PageRequest::read(&request);
+    #[test]
+    fn read_with_should_use_size_limit() {
+        let limit = SizeLimit::new(5, 1, 10);
+        let request = Request::default();
+        let result = PageRequest::read_with(&request, limit).unwrap();
 
-        assert_eq!(result.page, 1);
-        assert_eq!(result.size, SIZE_MIN);
-        assert_eq!(result.offset, 0);
+        assert_eq!(result.size, 5);
+        assert_eq!(result.size_limit, limit);
     }
 
     #[test]
@@ -94,10 +120,37 @@ mod tests {
         let query_map = QueryMap::from(query_params);
 
         let request = Request::default().with_query_string_parameters(query_map);
-        let result = PageRequest::read(&request);
+        let result = PageRequest::read(&request).unwrap();
 
         assert_eq!(result.page, 2);
         assert_eq!(result.size, 10);
         assert_eq!(result.offset, 10);
     }
+
+    #[test]
+    fn read_page_out_of_range_should_return_parameter() {
+        let query_params =
+            HashMap::<String, Vec<String>>::from([("page".into(), vec![i64::MAX.to_string()])]);
+        let query_map = QueryMap::from(query_params);
+
+        let request = Request::default().with_query_string_parameters(query_map);
+        let result = PageRequest::read(&request).err().unwrap();
+
+        assert_eq!(result.status(), 400);
+        assert_eq!(result.errors[0].source.parameter.as_deref(), Some("page"));
+    }
+
+    #[test]
+    fn read_invalid_size_should_return_parameter() {
+        let query_params =
+            HashMap::<String, Vec<String>>::from([("size".into(), vec!["abc".into()])]);
+        let query_map = QueryMap::from(query_params);
+
+        let request = Request::default().with_query_string_parameters(query_map);
+        let result = PageRequest::read(&request).err().unwrap();
+
+        assert_eq!(result.status(), 400);
+        assert_eq!(result.errors[0].code.as_str(), "invalid");
+        assert_eq!(result.errors[0].source.parameter.as_deref(), Some("size"));
+    }
 }
@@ -0,0 +1,70 @@
+use serde_json::Value;
+
+pub const CONTENT_TYPE_MERGE_PATCH: &str = "application/merge-patch+json";
+
+/// Applies a JSON Merge Patch (RFC 7386) to `target`. Members of the patch replace the members
+/// of the target, `null` removes them and objects are merged recursively. Arrays are replaced
+/// as a whole.
+pub fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(&key);
+            } else {
+                merge_patch(target.entry(key).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::merge_patch;
+
+    #[test]
+    fn merge_patch_should_follow_rfc_7386() {
+        let mut target = json!({
+            "title": "Goodbye!",
+            "author": { "givenName": "John", "familyName": "Doe" },
+            "tags": ["example", "sample"],
+            "content": "This will be unchanged"
+        });
+        let patch = json!({
+            "title": "Hello!",
+            "phoneNumber": "+01-123-456-7890",
+            "author": { "familyName": null },
+            "tags": ["example"]
+        });
+        merge_patch(&mut target, patch);
+
+        assert_eq!(
+            target,
+            json!({
+                "title": "Hello!",
+                "author": { "givenName": "John" },
+                "tags": ["example"],
+                "content": "This will be unchanged",
+                "phoneNumber": "+01-123-456-7890"
+            })
+        );
+    }
+
+    #[test]
+    fn merge_patch_not_object_should_replace_target() {
+        let mut target = json!({ "a": 1 });
+        merge_patch(&mut target, json!(["b"]));
+
+        assert_eq!(target, json!(["b"]));
+    }
+}
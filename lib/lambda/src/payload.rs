@@ -0,0 +1,293 @@
+use std::{collections::HashMap, env};
+
+use lambda_http::{http::header::CONTENT_TYPE, Request};
+use model::error::{
+    invalid_field, payload_too_large, required_body, required_field, unsupported_media_type,
+    ErrorResult,
+};
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde_json::{error::Category, Value};
+use serde_path_to_error::Segment;
+
+use crate::problem::CONTENT_TYPE_JSON;
+
+const SIZE_LIMIT_DEFAULT: usize = 1024 * 1024;
+
+/// The largest body in bytes that is read, `PAYLOAD_SIZE_LIMIT`. Defaults to 1 MiB.
+static SIZE_LIMIT: Lazy<usize> = Lazy::new(|| {
+    env::var("PAYLOAD_SIZE_LIMIT")
+        .ok()
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(SIZE_LIMIT_DEFAULT)
+});
+
+/// The body of the request if it has the media type that `accept` allows and is within the size
+/// limit. Otherwise the error is a `415` with the `expected` media type or a `413`.
+pub fn read_body<'a>(
+    request: &'a Request,
+    expected: &str,
+    accept: impl Fn(&str) -> bool,
+) -> Result<&'a [u8], ErrorResult> {
+    let body = request.body().as_ref();
+
+    if body.is_empty() {
+        return Err(required_body());
+    }
+
+    let media_type = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase());
+
+    if !media_type.is_some_and(|media_type| accept(&media_type)) {
+        return Err(unsupported_media_type(expected));
+    }
+
+    if body.len() > *SIZE_LIMIT {
+        return Err(payload_too_large(*SIZE_LIMIT));
+    }
+
+    Ok(body)
+}
+
+/// `application/json` or a JSON based media type such as `application/vnd.api+json`.
+pub fn is_json(media_type: &str) -> bool {
+    media_type == CONTENT_TYPE_JSON
+        || media_type
+            .strip_prefix("application/")
+            .is_some_and(|subtype| subtype.ends_with("+json"))
+}
+
+/// Deserializes the JSON body. A malformed body is an error with the line and column, a value
+/// that does not fit `T` is an error with the JSON pointer of the value and the expected type.
+pub fn from_slice<T: DeserializeOwned>(body: &[u8]) -> Result<T, ErrorResult> {
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(path_error)?;
+    deserializer
+        .end()
+        .map_err(|error| body_error("/body".to_owned(), error))?;
+
+    Ok(value)
+}
+
+/// Same as `from_slice` for a body that was already parsed, e.g. after a merge patch.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, ErrorResult> {
+    serde_path_to_error::deserialize(value).map_err(path_error)
+}
+
+fn path_error(error: serde_path_to_error::Error<serde_json::Error>) -> ErrorResult {
+    let pointer = pointer(error.path().iter());
+
+    body_error(pointer, error.into_inner())
+}
+
+fn body_error(pointer: String, error: serde_json::Error) -> ErrorResult {
+    if error.classify() != Category::Data {
+        let meta = HashMap::from([
+            ("line".to_owned(), Value::from(error.line())),
+            ("column".to_owned(), Value::from(error.column())),
+        ]);
+
+        return invalid_field("/body".to_owned(), meta);
+    }
+
+    match mismatch(&error.to_string()) {
+        Mismatch::Missing(field) => required_field(format!("{pointer}/{}", escape(field))),
+        Mismatch::Invalid(kind) => {
+            let meta = kind
+                .map(|kind| HashMap::from([("type".to_owned(), Value::from(kind))]))
+                .unwrap_or_default();
+
+            invalid_field(pointer, meta)
+        }
+    }
+}
+
+/// A value that did not fit the type it is deserialized into. serde only names the missing
+/// field or the expected type in the `Display` text of its errors, so that text is matched here
+/// and nowhere else.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Mismatch<'a> {
+    /// `missing field `name``.
+    Missing(&'a str),
+    /// The JSON type of what serde expected, if the message names one.
+    Invalid(Option<&'static str>),
+}
+
+pub(crate) fn mismatch(message: &str) -> Mismatch<'_> {
+    let missing = message
+        .strip_prefix("missing field `")
+        .and_then(|field| field.split('`').next());
+
+    match missing {
+        Some(field) => Mismatch::Missing(field),
+        None => Mismatch::Invalid(expected_type(message)),
+    }
+}
+
+/// The JSON pointer of a value in the body, e.g. `/body/translations/2/ordinal`.
+fn pointer<'a>(segments: impl Iterator<Item = &'a Segment>) -> String {
+    let mut pointer = "/body".to_owned();
+
+    for segment in segments {
+        match segment {
+            Segment::Seq { index } => pointer.push_str(&format!("/{index}")),
+            Segment::Map { key } => pointer.push_str(&format!("/{}", escape(key))),
+            Segment::Enum { variant } => pointer.push_str(&format!("/{}", escape(variant))),
+            Segment::Unknown => {}
+        }
+    }
+
+    pointer
+}
+
+/// Escapes a reference token of a JSON pointer as described in RFC 6901.
+fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// The JSON type of what serde expected, e.g. `integer` for `invalid type: string "a",
+/// expected i16 at line 1 column 12`.
+fn expected_type(message: &str) -> Option<&'static str> {
+    let expected = message.split(", expected ").nth(1)?;
+    let expected = expected.split(" at line ").next()?;
+    let integer = expected
+        .strip_prefix(['i', 'u'])
+        .is_some_and(|bits| bits == "size" || bits.parse::<u8>().is_ok());
+
+    let kind = match expected {
+        _ if integer || expected.contains("integer") => "integer",
+        "f32" | "f64" => "number",
+        _ if expected.contains("number") || expected.contains("Decimal") => "number",
+        _ if expected.contains("string") => "string",
+        _ if expected.contains("bool") => "boolean",
+        _ if expected.contains("sequence") || expected.contains("tuple") => "array",
+        _ if expected.starts_with("struct ") || expected.contains("map") => "object",
+        _ => return None,
+    };
+
+    Some(kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fmt::Debug};
+
+    use rust_decimal::Decimal;
+    use serde::{de::DeserializeOwned, Deserialize};
+    use serde_json::{json, Value};
+
+    use super::{from_slice, is_json, mismatch, Mismatch};
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Sample {
+        name: String,
+        #[serde(default)]
+        translations: Vec<Translation>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Translation {
+        ordinal: i16,
+    }
+
+    fn source(body: &str) -> (String, Value) {
+        let result = from_slice::<Sample>(body.as_bytes()).unwrap_err();
+        let error = &result.errors[0];
+        let source = serde_json::to_value(&error.source).unwrap();
+
+        (error.code.to_string(), source)
+    }
+
+    #[test]
+    fn from_slice_should_point_to_invalid_value() {
+        let body = r#"{"name":"a","translations":[{"ordinal":1},{"ordinal":2},{"ordinal":"3"}]}"#;
+
+        assert_eq!(
+            source(body),
+            (
+                "invalid".to_owned(),
+                json!({ "pointer": "/body/translations/2/ordinal", "meta": { "type": "integer" } })
+            )
+        );
+    }
+
+    #[test]
+    fn from_slice_should_point_to_missing_field() {
+        let body = r#"{"translations":[{}]}"#;
+
+        assert_eq!(
+            source(body),
+            (
+                "required".to_owned(),
+                json!({ "pointer": "/body/translations/0/ordinal" })
+            )
+        );
+    }
+
+    #[test]
+    fn from_slice_malformed_should_return_line_and_column() {
+        assert_eq!(
+            source("{\n\"name\": }"),
+            (
+                "invalid".to_owned(),
+                json!({ "pointer": "/body", "meta": { "line": 2, "column": 9 } })
+            )
+        );
+        assert_eq!(source(r#"{"name":"a"} x"#).1["pointer"], "/body");
+    }
+
+    /// The `Display` text of the error of deserializing `json` into `T`.
+    fn message<T: DeserializeOwned + Debug>(json: &str) -> String {
+        serde_json::from_str::<T>(json).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn mismatch_should_read_missing_field() {
+        let query = serde_html_form::from_str::<Sample>("")
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(
+            mismatch(&message::<Sample>("{}")),
+            Mismatch::Missing("name")
+        );
+        assert_eq!(mismatch(&query), Mismatch::Missing("name"));
+    }
+
+    #[test]
+    fn mismatch_should_read_expected_type() {
+        let cases = [
+            (message::<i16>(r#""1""#), Some("integer")),
+            (message::<u64>("-1"), Some("integer")),
+            (message::<usize>("true"), Some("integer")),
+            (message::<f64>(r#""1""#), Some("number")),
+            (message::<Decimal>("true"), Some("number")),
+            (message::<String>("1"), Some("string")),
+            (message::<bool>("1"), Some("boolean")),
+            (message::<Vec<i16>>("1"), Some("array")),
+            (message::<(i16, i16)>("1"), Some("array")),
+            (message::<Sample>("1"), Some("object")),
+            (message::<HashMap<String, i16>>("1"), Some("object")),
+            (message::<i16>("70000"), Some("integer")),
+            (message::<char>("1"), None),
+        ];
+
+        for (message, kind) in cases {
+            assert_eq!(mismatch(&message), Mismatch::Invalid(kind), "{message}");
+        }
+    }
+
+    #[test]
+    fn is_json_should_accept_json_suffix() {
+        assert!(is_json("application/json"));
+        assert!(is_json("application/vnd.api+json"));
+        assert!(!is_json("application/x-www-form-urlencoded"));
+        assert!(!is_json("text/json+plain"));
+    }
+}
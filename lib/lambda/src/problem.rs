@@ -0,0 +1,222 @@
+use std::env;
+
+use lambda_http::{
+    http::{header::ACCEPT, StatusCode},
+    Request,
+};
+use model::error::{ErrorDetail, ErrorResult};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+
+pub const CONTENT_TYPE_JSON: &str = "application/json";
+pub const CONTENT_TYPE_PROBLEM: &str = "application/problem+json";
+
+/// The format used when the client did not ask for a specific one.
+/// Set `ERROR_FORMAT=problem` on a function to default to `application/problem+json`.
+static FORMAT_DEFAULT: Lazy<ErrorFormat> = Lazy::new(|| match env::var("ERROR_FORMAT") {
+    Ok(value) if value.eq_ignore_ascii_case("problem") => ErrorFormat::Problem,
+    _ => ErrorFormat::Json,
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// Our own `{status, errors[]}` shape.
+    Json,
+    /// RFC 9457 problem details with the errors as an extension member.
+    Problem,
+}
+
+/// RFC 9457 (obsoletes RFC 7807) problem details object.
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+pub struct Problem<'a> {
+    #[serde(rename = "type")]
+    pub problem_type: &'a str,
+    pub title: &'a str,
+    pub status: u16,
+    pub detail: Option<String>,
+    pub instance: Option<&'a str>,
+    pub errors: &'a [ErrorDetail],
+    #[serde(rename = "requestId")]
+    pub request_id: Option<&'a str>,
+}
+
+impl<'a> Problem<'a> {
+    pub fn new(result: &'a ErrorResult, instance: Option<&'a str>) -> Self {
+        let title = StatusCode::from_u16(result.status())
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or("Unknown Error");
+
+        Self {
+            problem_type: "about:blank",
+            title,
+            status: result.status(),
+            detail: problem_detail(&result.errors),
+            instance,
+            errors: &result.errors,
+            request_id: result.request_id.as_deref(),
+        }
+    }
+}
+
+impl Default for ErrorFormat {
+    fn default() -> Self {
+        *FORMAT_DEFAULT
+    }
+}
+
+impl ErrorFormat {
+    /// Picks the format from the `Accept` header. Only explicitly listed media types count,
+    /// so wildcards and ties fall back to the default format.
+    pub fn negotiate(request: &Request) -> Self {
+        Self::from_accept(
+            request
+                .headers()
+                .get(ACCEPT)
+                .and_then(|value| value.to_str().ok()),
+            Self::default(),
+        )
+    }
+
+    pub fn from_accept(accept: Option<&str>, default: Self) -> Self {
+        let accept = match accept {
+            Some(accept) => accept,
+            None => return default,
+        };
+        let mut json = 0.0;
+        let mut problem = 0.0;
+
+        for range in accept.split(',') {
+            let mut parts = range.split(';').map(str::trim);
+            let media_type = parts.next().unwrap_or_default();
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if media_type.eq_ignore_ascii_case(CONTENT_TYPE_JSON) {
+                json = quality;
+            } else if media_type.eq_ignore_ascii_case(CONTENT_TYPE_PROBLEM) {
+                problem = quality;
+            }
+        }
+
+        if problem > json {
+            Self::Problem
+        } else if json > problem {
+            Self::Json
+        } else {
+            default
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json => CONTENT_TYPE_JSON,
+            Self::Problem => CONTENT_TYPE_PROBLEM,
+        }
+    }
+}
+
+fn problem_detail(errors: &[ErrorDetail]) -> Option<String> {
+    let details = errors
+        .iter()
+        .map(|error| {
+            if let Some(message) = &error.message {
+                return message.to_owned();
+            }
+
+            let source = &error.source;
+            let location = source
+                .pointer
+                .as_ref()
+                .or(source.parameter.as_ref())
+                .or(source.header.as_ref());
+
+            match location {
+                Some(location) => format!("{} at {location}", error.code),
+                None => error.code.to_string(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if details.is_empty() {
+        return None;
+    }
+
+    Some(details.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorFormat, Problem};
+    use model::error::{id_not_found, path_not_found};
+    use serde_json::json;
+
+    #[test]
+    fn from_accept_missing_should_return_default() {
+        let json = ErrorFormat::from_accept(None, ErrorFormat::Json);
+        let problem = ErrorFormat::from_accept(None, ErrorFormat::Problem);
+
+        assert_eq!(json, ErrorFormat::Json);
+        assert_eq!(problem, ErrorFormat::Problem);
+    }
+
+    #[test]
+    fn from_accept_wildcard_should_return_default() {
+        let result = ErrorFormat::from_accept(Some("*/*"), ErrorFormat::Problem);
+
+        assert_eq!(result, ErrorFormat::Problem);
+    }
+
+    #[test]
+    fn from_accept_problem_should_return_problem() {
+        let accept = Some("application/problem+json, */*;q=0.1");
+        let result = ErrorFormat::from_accept(accept, ErrorFormat::Json);
+
+        assert_eq!(result, ErrorFormat::Problem);
+    }
+
+    #[test]
+    fn from_accept_higher_quality_should_win() {
+        let accept = Some("application/problem+json;q=0.5, application/json");
+        let result = ErrorFormat::from_accept(accept, ErrorFormat::Problem);
+
+        assert_eq!(result, ErrorFormat::Json);
+    }
+
+    #[test]
+    fn problem_should_serialize_rfc_9457_members() {
+        let result = id_not_found("sample", 1);
+        let problem = Problem::new(&result, Some("/api/admin/samples/1"));
+        let value = serde_json::to_value(problem).unwrap();
+
+        assert_eq!(
+            value,
+            json!({
+                "type": "about:blank",
+                "title": "Not Found",
+                "status": 404,
+                "detail": "not_found at /data/sample/id",
+                "instance": "/api/admin/samples/1",
+                "errors": [{
+                    "id": 1,
+                    "code": "not_found",
+                    "source": { "pointer": "/data/sample/id" }
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn problem_without_instance_should_skip_instance() {
+        let result = path_not_found();
+        let problem = Problem::new(&result, None);
+        let value = serde_json::to_value(problem).unwrap();
+
+        assert_eq!(value.get("instance"), None);
+        assert_eq!(value["title"], "Not Found");
+    }
+}
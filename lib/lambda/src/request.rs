@@ -1,42 +1,168 @@
 use std::str::FromStr;
 
 use lambda_http::{
-    http::header::ACCEPT_LANGUAGE, request::RequestContext, Request, RequestExt, RequestPayloadExt,
+    http::header::{ACCEPT_LANGUAGE, IF_MATCH, IF_NONE_MATCH},
+    request::RequestContext,
+    Request, RequestExt,
 };
 use model::{
-    error::{invalid_body, required_body, required_parameter, unauthorized, ErrorResult},
-    validation::validate,
+    error::{
+        internal_server, invalid_header, invalid_parameter, precondition_required,
+        required_parameter, version_conflict, ErrorDetail, ErrorResult,
+    },
+    idempotency::IdempotencyKey,
+    precondition::IfMatch,
+    validation::{validate, validate_parameters},
 };
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use serde_path_to_error::Segment;
+use sha2::{Digest, Sha256};
+use tracing::Span;
 use validator::Validate;
 
+use crate::{
+    auth::{Access, Principal},
+    etag::{none_match, parse_entity_tag},
+    fields::{field_names, parse_fields, FIELDS},
+    jwt::{verifier, AlbClaims},
+    language::{fallback_chain, parse_accept_language},
+    patch::{merge_patch, CONTENT_TYPE_MERGE_PATCH},
+    payload::{from_slice, from_value, is_json, mismatch, read_body, Mismatch},
+    problem::CONTENT_TYPE_JSON,
+};
+
+pub const X_REQUEST_ID: &str = "x-request-id";
+const REQUEST_ID_MAX_LENGTH: usize = 200;
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+const IDEMPOTENCY_KEY_MAX_LENGTH: usize = 255;
+
 pub trait RequestExtension {
+    /// The caller of the request, as verified by the authorizer of a REST or HTTP API or a
+    /// Function URL, by the OIDC authentication of an ALB or, if `JWT_ISSUER` is configured, by
+    /// the in-process verifier.
+    fn get_principal(&self) -> Option<Principal>;
+
+    /// The caller of the request if it has the `access`. Otherwise the error is a `401` when
+    /// there is no caller or a `403` when the caller is missing the role or scope.
+    fn authorize(&self, access: Access) -> Result<Principal, ErrorResult>;
+
     fn get_user_id(&self) -> Result<String, ErrorResult>;
 
+    /// The correlation id of the request. This is the `X-Request-Id` header if the client sent
+    /// a usable one, otherwise the API Gateway `requestId`, otherwise the Lambda request id.
+    fn get_request_id(&self) -> Option<String>;
+
     fn path_param<T: FromStr>(&self, key: &str) -> Result<T, ErrorResult>;
 
     fn query_param<T: FromStr>(&self, key: &str) -> Option<T>;
 
-    fn query_version(&self) -> i16;
+    /// The `fields` query parameter, checked against the fields of the resource `T` before the
+    /// resource is read, e.g. `request.fields::<SampleList>()?` for a page of samples.
+    fn fields<'de, T: Deserialize<'de>>(&self) -> Result<Option<Vec<String>>, ErrorResult>;
 
-    fn get_language(&self) -> Option<String>;
+    /// The versions of the record `id` from the `If-Match` header, which lists the `ETag`s the
+    /// client got with the record, or `*` for any version. Without the header the error is a
+    /// `428` and when no tag is of the record a `412`.
+    fn if_match(&self, entity: &str, id: i64) -> Result<IfMatch, ErrorResult>;
 
+    /// Whether the client already has the version of `etag`, so that a `304` can be sent.
+    fn if_none_match(&self, etag: &str) -> bool;
+
+    /// The languages to serve from the most to the least preferred. These are the ranges of the
+    /// `Accept-Language` header, each followed by its less specific tags and then the default
+    /// language, e.g. `de-CH,en;q=0.8` is `de-ch`, `de`, `en`.
+    fn get_languages(&self) -> Vec<String>;
+
+    /// The `Idempotency-Key` header with the fingerprint of the method, path and body, or `None`
+    /// if the client did not send one. A JSON body is fingerprinted with sorted keys and without
+    /// whitespace, so a retry that formats it differently is the same payload. An empty key or
+    /// one longer than 255 characters is invalid.
+    fn idempotency_key(&self) -> Result<Option<IdempotencyKey>, ErrorResult>;
+
+    /// Deserializes and validates the JSON body. The error is a `415` for another `Content-Type`,
+    /// a `413` above `PAYLOAD_SIZE_LIMIT` bytes and otherwise points to the invalid value.
     fn validate_payload<P>(&self) -> Result<P, ErrorResult>
     where
         P: DeserializeOwned + Validate;
+
+    /// Applies the `application/merge-patch+json` body to `current` and validates the result
+    /// like a payload, e.g. `request.validate_merge_patch(&SampleRequest::from(&sample))`.
+    fn validate_merge_patch<P>(&self, current: &P) -> Result<P, ErrorResult>
+    where
+        P: Serialize + DeserializeOwned + Validate;
+
+    /// Deserializes the whole query string into `Q`, e.g. `?size=10&createdAt=...`. A key that
+    /// is repeated can be read into a `Vec`. A value that cannot be parsed or is not valid is an
+    /// error with the name of the field as the `parameter`, one for every invalid parameter.
+    fn validate_query<Q>(&self) -> Result<Q, ErrorResult>
+    where
+        Q: DeserializeOwned + Validate;
 }
 
 impl RequestExtension for Request {
-    fn get_user_id(&self) -> Result<String, ErrorResult> {
-        match self.request_context() {
-            RequestContext::ApiGatewayV2(http) => http
+    fn get_principal(&self) -> Option<Principal> {
+        let principal = match self.request_context_ref() {
+            Some(RequestContext::ApiGatewayV1(rest)) => {
+                Principal::from_fields(&rest.authorizer.fields).or_else(|| {
+                    let identity = &rest.identity;
+                    Principal::from_iam(identity.user_arn.as_deref(), identity.user.as_deref())
+                })
+            }
+            Some(RequestContext::ApiGatewayV2(http)) => http
                 .authorizer
-                .and_then(|a| a.jwt)
-                .map(|j| j.claims)
-                .and_then(|c| c.get("sub").map(|s| s.to_owned()))
-                .ok_or_else(unauthorized),
-            _ => Err(unauthorized()),
+                .as_ref()
+                .and_then(Principal::from_authorizer),
+            Some(RequestContext::Alb(_)) => self
+                .extensions()
+                .get::<AlbClaims>()
+                .and_then(|AlbClaims(jwt)| Principal::from_jwt(jwt)),
+            _ => None,
+        };
+        let principal = principal.or_else(|| {
+            let jwt = verifier()?.verify_headers(self.headers())?;
+            Principal::from_jwt(&jwt)
+        });
+
+        principal.inspect(|principal| {
+            Span::current().record("user_id", &principal.sub);
+        })
+    }
+
+    fn authorize(&self, access: Access) -> Result<Principal, ErrorResult> {
+        access.check(self.get_principal())
+    }
+
+    fn get_user_id(&self) -> Result<String, ErrorResult> {
+        self.authorize(Access::Authenticated)
+            .map(|principal| principal.sub)
+    }
+
+    fn get_request_id(&self) -> Option<String> {
+        let header = self
+            .headers()
+            .get(X_REQUEST_ID)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty() && value.len() <= REQUEST_ID_MAX_LENGTH);
+
+        if let Some(header) = header {
+            return Some(header.to_owned());
         }
+
+        let context = self
+            .request_context_ref()
+            .and_then(|context| match context {
+                RequestContext::ApiGatewayV1(rest) => rest.request_id.to_owned(),
+                RequestContext::ApiGatewayV2(http) => http.request_id.to_owned(),
+                RequestContext::WebSocket(socket) => socket.request_id.to_owned(),
+                _ => None,
+            });
+
+        context.or_else(|| {
+            self.lambda_context_ref()
+                .map(|context| context.request_id.to_owned())
+        })
     }
 
     fn path_param<T: FromStr>(&self, key: &str) -> Result<T, ErrorResult> {
@@ -50,23 +176,450 @@ impl RequestExtension for Request {
             .and_then(|query| query.first(key)?.parse::<T>().ok())
     }
 
-    fn query_version(&self) -> i16 {
-        self.query_param("version").unwrap_or_default()
+    fn fields<'de, T: Deserialize<'de>>(&self) -> Result<Option<Vec<String>>, ErrorResult> {
+        self.query_string_parameters_ref()
+            .and_then(|query| query.first(FIELDS))
+            .map(|fields| parse_fields(fields, field_names::<T>()))
+            .transpose()
+    }
+
+    fn if_match(&self, entity: &str, id: i64) -> Result<IfMatch, ErrorResult> {
+        let header = self
+            .headers()
+            .get(IF_MATCH)
+            .ok_or_else(precondition_required)?
+            .to_str()
+            .map_err(|_| invalid_header(IF_MATCH.as_str()))?;
+
+        if header.trim() == "*" {
+            return Ok(IfMatch::Any);
+        }
+
+        let tags = header
+            .split(',')
+            .map(parse_entity_tag)
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid_header(IF_MATCH.as_str()))?;
+        let versions = tags
+            .iter()
+            .filter(|(tag_id, _)| *tag_id == id)
+            .map(|(_, version)| *version)
+            .collect::<Vec<_>>();
+
+        match tags.first() {
+            Some((_, version)) if versions.is_empty() => {
+                Err(version_conflict(entity, id, *version))
+            }
+            _ => Ok(IfMatch::Versions(versions)),
+        }
     }
 
-    fn get_language(&self) -> Option<String> {
+    fn if_none_match(&self, etag: &str) -> bool {
         self.headers()
+            .get(IF_NONE_MATCH)
+            .and_then(|header| header.to_str().ok())
+            .is_some_and(|header| none_match(header, etag))
+    }
+
+    fn get_languages(&self) -> Vec<String> {
+        let ranges = self
+            .headers()
             .get(ACCEPT_LANGUAGE)
-            .and_then(|value| value.to_str().map(|s| s.to_string()).ok())
+            .and_then(|value| value.to_str().ok())
+            .map(parse_accept_language)
+            .unwrap_or_default();
+
+        fallback_chain(&ranges)
+    }
+
+    fn idempotency_key(&self) -> Result<Option<IdempotencyKey>, ErrorResult> {
+        let Some(header) = self.headers().get(IDEMPOTENCY_KEY) else {
+            return Ok(None);
+        };
+        let key = header
+            .to_str()
+            .map(str::trim)
+            .ok()
+            .filter(|key| !key.is_empty() && key.len() <= IDEMPOTENCY_KEY_MAX_LENGTH)
+            .ok_or_else(|| invalid_header(IDEMPOTENCY_KEY))?;
+        let body = self.body();
+        let payload = serde_json::from_slice::<Value>(body)
+            .ok()
+            .and_then(|json| serde_json::to_vec(&json).ok());
+        let fingerprint = Sha256::new()
+            .chain_update(self.method().as_str())
+            .chain_update(" ")
+            .chain_update(self.uri().path())
+            .chain_update("\n")
+            .chain_update(payload.as_deref().unwrap_or(body))
+            .finalize();
+
+        Ok(Some(IdempotencyKey {
+            key: key.to_owned(),
+            fingerprint: hex::encode(fingerprint),
+        }))
     }
 
     fn validate_payload<P>(&self) -> Result<P, ErrorResult>
     where
         P: DeserializeOwned + Validate,
     {
-        match self.payload::<P>() {
-            Ok(value) => value.ok_or_else(required_body).and_then(validate),
-            Err(_) => Err(invalid_body()),
+        read_body(self, CONTENT_TYPE_JSON, is_json)
+            .and_then(from_slice)
+            .and_then(validate)
+    }
+
+    fn validate_merge_patch<P>(&self, current: &P) -> Result<P, ErrorResult>
+    where
+        P: Serialize + DeserializeOwned + Validate,
+    {
+        let body = read_body(self, CONTENT_TYPE_MERGE_PATCH, |media_type| {
+            media_type == CONTENT_TYPE_MERGE_PATCH
+        })?;
+        let patch = from_slice::<Value>(body)?;
+        let mut target = serde_json::to_value(current).map_err(|_| internal_server())?;
+        merge_patch(&mut target, patch);
+
+        from_value::<P>(target).and_then(validate)
+    }
+
+    fn validate_query<Q>(&self) -> Result<Q, ErrorResult>
+    where
+        Q: DeserializeOwned + Validate,
+    {
+        let mut pairs = self
+            .query_string_parameters_ref()
+            .map(|query| {
+                query
+                    .iter()
+                    .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let mut errors = Vec::<ErrorDetail>::new();
+
+        // serde stops at the first value that does not fit, so each invalid parameter is left
+        // out and the rest is read again until it fits or a required parameter is missing.
+        loop {
+            let query = form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(&pairs)
+                .finish();
+            let deserializer =
+                serde_html_form::Deserializer::new(form_urlencoded::parse(query.as_bytes()));
+
+            let result = match serde_path_to_error::deserialize::<_, Q>(deserializer) {
+                Ok(query) if errors.is_empty() => return validate_parameters(query),
+                Ok(query) => {
+                    if let Err(result) = validate_parameters(query) {
+                        errors.extend(result.errors);
+                    }
+
+                    return Err(ErrorResult::new(errors));
+                }
+                Err(error) => query_error(error),
+            };
+            let name = result
+                .errors
+                .first()
+                .and_then(|error| error.source.parameter.to_owned())
+                .unwrap_or_default();
+            let reported = errors
+                .iter()
+                .any(|error| error.source.parameter.as_ref() == Some(&name));
+            let count = pairs.len();
+            pairs.retain(|(key, _)| *key != name);
+
+            if !reported {
+                errors.extend(result.errors);
+            }
+
+            if reported || pairs.len() == count {
+                return Err(ErrorResult::new(errors));
+            }
         }
     }
 }
+
+fn query_error(error: serde_path_to_error::Error<serde_html_form::de::Error>) -> ErrorResult {
+    match mismatch(&error.inner().to_string()) {
+        Mismatch::Missing(field) => required_parameter(field),
+        Mismatch::Invalid(_) => invalid_parameter(parameter(error.path())),
+    }
+}
+
+/// The name of the parameter of a path, e.g. `lang` for `lang[1]` of a repeated parameter.
+fn parameter(path: &serde_path_to_error::Path) -> String {
+    path.iter()
+        .find_map(|segment| match segment {
+            Segment::Map { key } => Some(key.to_owned()),
+            _ => None,
+        })
+        .unwrap_or_else(|| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{RequestExtension, X_REQUEST_ID};
+    use lambda_http::{
+        aws_lambda_events::{apigw::ApiGatewayV2httpRequestContext, query_map::QueryMap},
+        request::RequestContext,
+        Body, Request, RequestExt,
+    };
+    use model::precondition::IfMatch;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    fn http_context(request_id: &str) -> RequestContext {
+        let context = ApiGatewayV2httpRequestContext {
+            request_id: Some(request_id.into()),
+            ..Default::default()
+        };
+
+        RequestContext::ApiGatewayV2(context)
+    }
+
+    #[test]
+    fn get_request_id_should_prefer_header() {
+        let mut request = Request::default().with_request_context(http_context("gateway"));
+        request
+            .headers_mut()
+            .insert(X_REQUEST_ID, "client".parse().unwrap());
+
+        assert_eq!(request.get_request_id().as_deref(), Some("client"));
+    }
+
+    #[test]
+    fn get_request_id_should_fall_back_to_gateway() {
+        let mut request = Request::default().with_request_context(http_context("gateway"));
+        request
+            .headers_mut()
+            .insert(X_REQUEST_ID, " ".parse().unwrap());
+
+        assert_eq!(request.get_request_id().as_deref(), Some("gateway"));
+    }
+
+    #[test]
+    fn get_request_id_should_ignore_long_header() {
+        let mut request = Request::default();
+        let value = "a".repeat(201);
+        request
+            .headers_mut()
+            .insert(X_REQUEST_ID, value.parse().unwrap());
+
+        assert_eq!(request.get_request_id(), None);
+    }
+
+    #[test]
+    fn get_languages_should_return_fallback_chain() {
+        let mut request = Request::default();
+        request.headers_mut().insert(
+            "accept-language",
+            "en;q=0.8, de-CH, *;q=0.5".parse().unwrap(),
+        );
+
+        assert_eq!(request.get_languages(), vec!["de-ch", "de", "en"]);
+    }
+
+    #[test]
+    fn idempotency_key_should_fingerprint_payload() {
+        let request = |key: &str, body: &str| {
+            let mut request = Request::new(Body::from(body));
+            request
+                .headers_mut()
+                .insert("idempotency-key", key.parse().unwrap());
+            request
+        };
+        let key = request(" key-1 ", "{}").idempotency_key().unwrap().unwrap();
+
+        assert_eq!(key.key, "key-1");
+        assert_eq!(
+            request("key-1", "{}").idempotency_key().unwrap(),
+            Some(key.clone())
+        );
+        assert_eq!(
+            request("key-1", r#"{ "b": [1, 2], "a": { "d": null, "c": "x" } }"#)
+                .idempotency_key()
+                .unwrap()
+                .unwrap()
+                .fingerprint,
+            request("key-1", r#"{"a":{"c":"x","d":null},"b":[1,2]}"#)
+                .idempotency_key()
+                .unwrap()
+                .unwrap()
+                .fingerprint
+        );
+        assert_ne!(
+            request("key-1", "{\"name\":\"a\"}")
+                .idempotency_key()
+                .unwrap()
+                .unwrap()
+                .fingerprint,
+            key.fingerprint
+        );
+        assert_eq!(
+            request(" ", "{}").idempotency_key().unwrap_err().status(),
+            400
+        );
+        assert_eq!(Request::default().idempotency_key().unwrap(), None);
+    }
+
+    #[test]
+    fn if_match_should_read_version_of_record() {
+        let request = |if_match: Option<&str>| {
+            let mut request = Request::default();
+            if let Some(if_match) = if_match {
+                request
+                    .headers_mut()
+                    .insert("if-match", if_match.parse().unwrap());
+            }
+            request
+        };
+
+        assert_eq!(
+            request(Some(r#""7-3""#)).if_match("sample", 7).unwrap(),
+            IfMatch::Versions(vec![3])
+        );
+        assert_eq!(
+            request(Some(r#""7-2", "8-4", "7-3""#))
+                .if_match("sample", 7)
+                .unwrap(),
+            IfMatch::Versions(vec![2, 3])
+        );
+        assert_eq!(
+            request(Some(" * ")).if_match("sample", 7).unwrap(),
+            IfMatch::Any
+        );
+        assert_eq!(
+            request(None).if_match("sample", 7).unwrap_err().status(),
+            428
+        );
+        assert_eq!(
+            request(Some(r#""8-3""#))
+                .if_match("sample", 7)
+                .unwrap_err()
+                .status(),
+            412
+        );
+        assert_eq!(
+            request(Some(r#""8-3", "9-3""#))
+                .if_match("sample", 7)
+                .unwrap_err()
+                .status(),
+            412
+        );
+        assert_eq!(
+            request(Some(r#""7-3", abc"#))
+                .if_match("sample", 7)
+                .unwrap_err()
+                .status(),
+            400
+        );
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, Validate)]
+    struct Patchable {
+        #[validate(length(min = 1))]
+        name: String,
+        amount: Option<i32>,
+    }
+
+    #[test]
+    fn validate_merge_patch_should_patch_and_validate() {
+        let current = Patchable {
+            name: "a".to_owned(),
+            amount: Some(1),
+        };
+        let request = |content_type: &str, body: &str| {
+            let mut request = Request::new(Body::from(body));
+            request
+                .headers_mut()
+                .insert("content-type", content_type.parse().unwrap());
+            request
+        };
+        let patched = request("application/merge-patch+json", r#"{"amount":null}"#)
+            .validate_merge_patch(&current)
+            .unwrap();
+
+        assert_eq!(patched.name, "a");
+        assert_eq!(patched.amount, None);
+        assert_eq!(
+            request("application/merge-patch+json", r#"{"name":""}"#)
+                .validate_merge_patch(&current)
+                .unwrap_err()
+                .status(),
+            400
+        );
+        assert_eq!(
+            request("application/json", "{}")
+                .validate_merge_patch(&current)
+                .unwrap_err()
+                .status(),
+            415
+        );
+    }
+
+    #[derive(Debug, Deserialize, Validate)]
+    #[allow(dead_code)]
+    struct Query {
+        #[validate(range(min = 1))]
+        size: Option<i16>,
+        id: Option<i64>,
+        page: i64,
+        #[serde(default)]
+        lang: Vec<String>,
+    }
+
+    #[test]
+    fn validate_query_should_report_every_invalid_parameter() {
+        let request = |query: &str| {
+            let query = serde_html_form::from_str::<Vec<(String, String)>>(query).unwrap();
+            let mut query_map = HashMap::<String, Vec<String>>::new();
+            for (key, value) in query {
+                query_map.entry(key).or_default().push(value);
+            }
+
+            Request::default().with_query_string_parameters(QueryMap::from(query_map))
+        };
+        let parameters = |query: &str| {
+            let result = request(query).validate_query::<Query>().unwrap_err();
+            let mut errors = result
+                .errors
+                .iter()
+                .map(|error| {
+                    let parameter = error.source.parameter.to_owned().unwrap_or_default();
+                    (parameter, error.code.to_string())
+                })
+                .collect::<Vec<_>>();
+            errors.sort();
+
+            errors
+        };
+        let error = |parameter: &str, code: &str| (parameter.to_owned(), code.to_owned());
+
+        assert_eq!(
+            request("page=1&lang=de&lang=en")
+                .validate_query::<Query>()
+                .unwrap()
+                .lang,
+            ["de", "en"]
+        );
+        assert_eq!(
+            parameters("page=1&id=a&size=b"),
+            [error("id", "invalid"), error("size", "invalid")]
+        );
+        assert_eq!(
+            parameters("page=1&id=a&size=0"),
+            [error("id", "invalid"), error("size", "range")]
+        );
+        assert_eq!(
+            parameters("page=a&id=a"),
+            [error("id", "invalid"), error("page", "invalid")]
+        );
+        assert_eq!(
+            parameters("id=a"),
+            [error("id", "invalid"), error("page", "required")]
+        );
+    }
+}
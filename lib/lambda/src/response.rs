@@ -0,0 +1,149 @@
+use lambda_http::http::header::{HeaderName, LOCATION, SET_COOKIE};
+
+use crate::{export::Export, json::JsonBody};
+
+/// A successful response of a handler, e.g. `JsonResponse::created(sample, location)` or
+/// `JsonResponse::no_content()`. A response without a body is sent without one, and so is
+/// any `204` or `304`.
+pub struct JsonResponse<T> {
+    pub status: u16,
+    pub headers: Vec<(HeaderName, String)>,
+    pub body: Option<T>,
+    /// The fields of the resources to send, see `RequestExtension::fields`.
+    pub fields: Option<Vec<String>>,
+}
+
+impl<T> JsonResponse<T> {
+    pub fn new(status: u16, body: T) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Some(body),
+            fields: None,
+        }
+    }
+
+    pub fn ok(body: T) -> Self {
+        Self::new(200, body)
+    }
+
+    /// A `201` with the `Location` of the created resource.
+    pub fn created(body: T, location: impl Into<String>) -> Self {
+        Self::new(201, body).header(LOCATION, location)
+    }
+
+    /// Sends only the `fields` of the resources, or all of them for `None`.
+    pub fn fields(mut self, fields: Option<Vec<String>>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    pub fn header(mut self, name: HeaderName, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    /// Adds a `Set-Cookie` header, e.g. `session=abc; Path=/; Secure; HttpOnly`.
+    /// API Gateway sends every cookie, not only the last one.
+    pub fn cookie(self, cookie: impl Into<String>) -> Self {
+        self.header(SET_COOKIE, cookie)
+    }
+}
+
+impl JsonResponse<()> {
+    pub fn no_content() -> Self {
+        Self {
+            status: 204,
+            headers: Vec::new(),
+            body: None,
+            fields: None,
+        }
+    }
+}
+
+/// A `200` with an export as an attachment, e.g. `samples.csv` for the name `samples`.
+pub struct ExportResponse {
+    pub export: Export,
+    pub name: String,
+    pub headers: Vec<(HeaderName, String)>,
+}
+
+impl ExportResponse {
+    pub fn new(export: Export, name: impl Into<String>) -> Self {
+        Self {
+            export,
+            name: name.into(),
+            headers: Vec::new(),
+        }
+    }
+
+    pub fn header(mut self, name: HeaderName, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+}
+
+/// The response of a handler that negotiates the format of a list, either the JSON or an export.
+pub enum Negotiated<T> {
+    Json(JsonResponse<T>),
+    Export(ExportResponse),
+}
+
+/// What a handler returns on success, either a `JsonResponse`, a `(status, body)` tuple or a
+/// `Negotiated` response.
+pub trait IntoResponse {
+    type Body: JsonBody;
+
+    fn into_response(self) -> Negotiated<Self::Body>;
+}
+
+impl<T: JsonBody> IntoResponse for JsonResponse<T> {
+    type Body = T;
+
+    fn into_response(self) -> Negotiated<T> {
+        Negotiated::Json(self)
+    }
+}
+
+impl<T: JsonBody> IntoResponse for (u16, T) {
+    type Body = T;
+
+    fn into_response(self) -> Negotiated<T> {
+        Negotiated::Json(JsonResponse::new(self.0, self.1))
+    }
+}
+
+impl<T: JsonBody> IntoResponse for Negotiated<T> {
+    type Body = T;
+
+    fn into_response(self) -> Negotiated<T> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lambda_http::http::header::{LOCATION, SET_COOKIE};
+
+    use super::{IntoResponse, JsonResponse, Negotiated};
+
+    #[test]
+    fn into_response_should_keep_tuple_and_headers() {
+        let Negotiated::Json(tuple) = (200, "a").into_response() else {
+            panic!("not a JSON response");
+        };
+        assert_eq!((tuple.status, tuple.body), (200, Some("a")));
+
+        let created = JsonResponse::created("a", "/api/admin/samples/1").cookie("a=b; Secure");
+        assert_eq!(
+            created.headers,
+            vec![
+                (LOCATION, "/api/admin/samples/1".to_owned()),
+                (SET_COOKIE, "a=b; Secure".to_owned())
+            ]
+        );
+
+        let empty = JsonResponse::no_content();
+        assert_eq!((empty.status, empty.body), (204, None));
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+
+use lambda_http::{http::Method, request::RequestContext, Request, RequestExt};
+use model::error::{path_not_found, ErrorResult};
+
+/// One segment of a route path. `{id}` matches a single segment and `{proxy+}` the rest of
+/// the path, the same as the API Gateway route keys.
+#[derive(Debug)]
+enum Segment {
+    Static(&'static str),
+    Param(&'static str),
+    Greedy(&'static str),
+}
+
+#[derive(Debug)]
+struct Route<R> {
+    key: &'static str,
+    method: Option<Method>,
+    segments: Vec<Segment>,
+    value: R,
+}
+
+/// Matches requests against API Gateway route keys, e.g. `GET /api/admin/samples/{id}`.
+/// `ANY` matches every method. When more than one route matches, the one with the most static
+/// segments wins.
+#[derive(Debug)]
+pub struct Router<R> {
+    routes: Vec<Route<R>>,
+}
+
+impl<R: Copy> Router<R> {
+    pub fn new(routes: &[(&'static str, R)]) -> Self {
+        let routes = routes
+            .iter()
+            .map(|(key, value)| {
+                let (method, path) = key.split_once(' ').unwrap_or(("ANY", key));
+                let method = match method {
+                    "ANY" => None,
+                    method => Some(
+                        Method::from_bytes(method.as_bytes())
+                            .unwrap_or_else(|_| panic!("{key} does not have a valid method")),
+                    ),
+                };
+                let segments = path
+                    .split('/')
+                    .filter(|segment| !segment.is_empty())
+                    .map(|segment| match segment.strip_prefix('{') {
+                        Some(name) => match name.strip_suffix("+}") {
+                            Some(name) => Segment::Greedy(name),
+                            None => Segment::Param(name.trim_end_matches('}')),
+                        },
+                        None => Segment::Static(segment),
+                    })
+                    .collect();
+
+                Route {
+                    key,
+                    method,
+                    segments,
+                    value: *value,
+                }
+            })
+            .collect();
+
+        Self { routes }
+    }
+
+    /// The matched route and the path parameters taken from the path.
+    pub fn find(&self, method: &Method, path: &str) -> Option<(R, HashMap<String, String>)> {
+        let parts = path
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>();
+
+        self.routes
+            .iter()
+            .filter(|route| route.method.as_ref().is_none_or(|m| m == method))
+            .filter_map(|route| {
+                let params = route.matches(&parts)?;
+                let score = route
+                    .segments
+                    .iter()
+                    .filter(|segment| matches!(segment, Segment::Static(_)))
+                    .count();
+
+                Some((score, route.value, params))
+            })
+            .max_by_key(|(score, _, _)| *score)
+            .map(|(_, value, params)| (value, params))
+    }
+
+    /// The route with the exact same key, e.g. the `routeKey` of an API Gateway HTTP API event.
+    pub fn get(&self, key: &str) -> Option<R> {
+        self.routes
+            .iter()
+            .find(|route| route.key == key)
+            .map(|route| route.value)
+    }
+
+    /// When API Gateway already matched one of the routes, its `routeKey` is used as is and so
+    /// are the path parameters it extracted. Otherwise, e.g. for the `$default` route, the
+    /// route is matched by method and path and the path parameters are set on the request.
+    pub fn route(&self, request: Request) -> (Option<R>, Request) {
+        let matched = match request.request_context_ref() {
+            Some(RequestContext::ApiGatewayV2(http)) => http.route_key.as_deref(),
+            _ => None,
+        }
+        .and_then(|key| self.get(key));
+
+        if let Some(value) = matched {
+            return (Some(value), request);
+        }
+
+        match self.find(request.method(), request.uri().path()) {
+            Some((value, params)) => (Some(value), request.with_path_parameters(params)),
+            None => (None, request),
+        }
+    }
+}
+
+/// Answers every request that does not match a route.
+pub async fn not_found(_: Request) -> Result<(u16, ()), ErrorResult> {
+    Err(path_not_found())
+}
+
+impl<R> Route<R> {
+    fn matches(&self, parts: &[&str]) -> Option<HashMap<String, String>> {
+        let mut params = HashMap::new();
+        let mut parts = parts.iter();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Static(name) => {
+                    if parts.next()? != name {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    params.insert(name.to_string(), parts.next()?.to_string());
+                }
+                Segment::Greedy(name) => {
+                    let rest = parts.by_ref().copied().collect::<Vec<_>>();
+                    if rest.is_empty() {
+                        return None;
+                    }
+                    params.insert(name.to_string(), rest.join("/"));
+                }
+            }
+        }
+
+        parts.next().is_none().then_some(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use lambda_http::{
+        aws_lambda_events::apigw::ApiGatewayV2httpRequestContext, http::Method,
+        request::RequestContext, Request, RequestExt,
+    };
+
+    use super::Router;
+
+    const ROUTES: &[(&str, u8)] = &[
+        ("GET /api/samples", 1),
+        ("GET /api/samples/{id}", 2),
+        ("GET /api/samples/latest", 3),
+        ("ANY /files/{path+}", 4),
+    ];
+
+    #[test]
+    fn find_should_return_path_parameters() {
+        let router = Router::new(ROUTES);
+        let (value, params) = router.find(&Method::GET, "/api/samples/10").unwrap();
+
+        assert_eq!(value, 2);
+        assert_eq!(params["id"], "10");
+    }
+
+    #[test]
+    fn find_should_prefer_static_segments() {
+        let router = Router::new(ROUTES);
+
+        assert_eq!(
+            router.find(&Method::GET, "/api/samples/latest").unwrap().0,
+            3
+        );
+        assert_eq!(router.find(&Method::GET, "/api/samples/").unwrap().0, 1);
+    }
+
+    #[test]
+    fn find_should_match_greedy_and_any_method() {
+        let router = Router::new(ROUTES);
+        let (value, params) = router.find(&Method::DELETE, "/files/a/b.txt").unwrap();
+
+        assert_eq!(value, 4);
+        assert_eq!(params["path"], "a/b.txt");
+    }
+
+    #[test]
+    fn find_should_not_match_other_method_or_path() {
+        let router = Router::new(ROUTES);
+
+        assert!(router.find(&Method::POST, "/api/samples").is_none());
+        assert!(router.find(&Method::GET, "/api/samples/1/items").is_none());
+        assert!(router.find(&Method::GET, "/files").is_none());
+    }
+
+    #[test]
+    fn route_should_set_path_parameters() {
+        let router = Router::new(ROUTES);
+        let request = Request::new("".into());
+        let (mut parts, body) = request.into_parts();
+        parts.uri = "/api/samples/7".parse().unwrap();
+        let (value, request) = router.route(Request::from_parts(parts, body));
+
+        assert_eq!(value, Some(2));
+        assert_eq!(
+            request.path_parameters_ref().unwrap().first("id"),
+            Some("7")
+        );
+    }
+
+    #[test]
+    fn route_should_use_route_key() {
+        let router = Router::new(ROUTES);
+        let context = ApiGatewayV2httpRequestContext {
+            route_key: Some("GET /api/samples/latest".to_owned()),
+            ..Default::default()
+        };
+        let params = HashMap::from([("id".to_owned(), "latest".to_owned())]);
+        let request = Request::default()
+            .with_request_context(RequestContext::ApiGatewayV2(context))
+            .with_path_parameters(params);
+        let (value, request) = router.route(request);
+
+        assert_eq!(value, Some(3));
+        assert_eq!(
+            request.path_parameters_ref().unwrap().first("id"),
+            Some("latest")
+        );
+    }
+}
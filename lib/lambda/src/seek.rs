@@ -1,31 +1,96 @@
 use crate::request::RequestExtension;
 use lambda_http::Request;
-use model::seek::SeekRequest;
-use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use model::{error::ErrorResult, limit::SizeLimit, seek::SeekRequest};
+use serde::Deserialize;
+use time::OffsetDateTime;
+use validator::Validate;
 
-const SIZE_DEFAULT: i16 = 20;
-const SIZE_MIN: i16 = 1;
+#[derive(Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+struct SeekQuery {
+    size: Option<i16>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    created_at: Option<OffsetDateTime>,
+    id: Option<i64>,
+}
+
+pub trait ApiSeekRequest: Sized {
+    fn read(request: &Request) -> Result<Self, ErrorResult> {
+        Self::read_with(request, SizeLimit::DEFAULT)
+    }
 
-pub trait ApiSeekRequest {
-    fn read(request: &Request) -> SeekRequest;
+    /// Same as `read` with the page sizes of the endpoint.
+    fn read_with(request: &Request, size_limit: SizeLimit) -> Result<Self, ErrorResult>;
 }
 
 impl ApiSeekRequest for SeekRequest {
-    fn read(request: &Request) -> SeekRequest {
-        let size = request
-            .query_param("size")
-            .unwrap_or(SIZE_DEFAULT)
-            .max(SIZE_MIN);
-        let created_at = request
-            .query_param::<String>("createdAt")
-            .and_then(|value| OffsetDateTime::parse(&value, &Rfc3339).ok());
-        let id = request.query_param("id");
-
-        SeekRequest {
+    fn read_with(request: &Request, size_limit: SizeLimit) -> Result<Self, ErrorResult> {
+        let query = request.validate_query::<SeekQuery>()?;
+        let size = size_limit.size(query.size)?;
+
+        Ok(SeekRequest {
             size,
             limit: size + 1,
-            created_at,
-            id,
-        }
+            created_at: query.created_at,
+            id: query.id,
+            size_limit,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use lambda_http::{aws_lambda_events::query_map::QueryMap, Request, RequestExt};
+    use model::seek::SeekRequest;
+
+    use super::ApiSeekRequest;
+
+    fn request(query: &[(&str, &str)]) -> Request {
+        let query_params = query
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        Request::default().with_query_string_parameters(QueryMap::from(query_params))
+    }
+
+    #[test]
+    fn read_should_parse_cursor() {
+        let request = request(&[("createdAt", "2024-03-01T10:00:00Z"), ("id", "5")]);
+        let result = SeekRequest::read(&request).unwrap();
+
+        assert_eq!(result.size, 20);
+        assert_eq!(result.limit, 21);
+        assert_eq!(result.created_at.unwrap().unix_timestamp(), 1709287200);
+        assert_eq!(result.id, Some(5));
+    }
+
+    #[test]
+    fn read_malformed_created_at_should_return_parameter() {
+        let request = request(&[("createdAt", "yesterday")]);
+        let result = SeekRequest::read(&request).err().unwrap();
+
+        assert_eq!(result.status(), 400);
+        assert_eq!(
+            result.errors[0].source.parameter.as_deref(),
+            Some("createdAt")
+        );
+    }
+
+    #[test]
+    fn read_malformed_cursor_should_return_every_parameter() {
+        let request = request(&[("createdAt", "yesterday"), ("id", "last")]);
+        let result = SeekRequest::read(&request).err().unwrap();
+        let mut parameters = result
+            .errors
+            .iter()
+            .filter_map(|error| error.source.parameter.as_deref())
+            .collect::<Vec<_>>();
+        parameters.sort_unstable();
+
+        assert_eq!(result.status(), 400);
+        assert_eq!(parameters, ["createdAt", "id"]);
     }
 }
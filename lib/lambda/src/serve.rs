@@ -0,0 +1,102 @@
+use std::future::Future;
+
+use lambda_http::{run, run_with_streaming_response, service_fn, Body, Error, Request, Response};
+use model::error::ErrorResult;
+use tokio::runtime::Builder;
+use tracing::error;
+
+use crate::{
+    json::json_handler,
+    jwt::init_verifier,
+    response::IntoResponse,
+    stream::{stream_handler, RowStream},
+    telemetry::flush,
+    tracing::init_tracing,
+};
+
+/// The entry point of a function, e.g. `serve(SampleService::new, admin::page)`.
+/// The state is created once per execution environment and shared by every invocation.
+pub fn serve<S, E, I, IF, H, F, R>(init: I, handler: H) -> Result<(), Error>
+where
+    S: Sync + 'static,
+    I: FnOnce() -> IF,
+    IF: Future<Output = Result<S, E>>,
+    E: Into<Error>,
+    H: Fn(&'static S, Request) -> F + Copy + Send,
+    F: Future<Output = Result<R, ErrorResult>> + Send,
+    R: IntoResponse + Send,
+{
+    serve_response(init, move |state, request| {
+        json_handler(request, move |request| handler(state, request))
+    })
+}
+
+/// Same as `serve` for handlers that build the response themselves, e.g. a router.
+pub fn serve_response<S, E, I, IF, H, F>(init: I, handler: H) -> Result<(), Error>
+where
+    S: Sync + 'static,
+    I: FnOnce() -> IF,
+    IF: Future<Output = Result<S, E>>,
+    E: Into<Error>,
+    H: Fn(&'static S, Request) -> F,
+    F: Future<Output = Result<Response<Body>, Error>> + Send,
+{
+    let runtime = Builder::new_multi_thread().enable_all().build()?;
+
+    runtime.block_on(async move {
+        let state = init_state(init).await?;
+
+        run(service_fn(|request| handler(state, request))).await
+    })
+}
+
+/// Same as `serve` for a handler that streams its rows, e.g. `serve_stream(SampleService::new,
+/// admin::export)`. The function must be invoked with response streaming, such as through a
+/// function URL with the `RESPONSE_STREAM` invoke mode.
+pub fn serve_stream<S, E, I, IF, H, F>(init: I, handler: H) -> Result<(), Error>
+where
+    S: Sync + 'static,
+    I: FnOnce() -> IF,
+    IF: Future<Output = Result<S, E>>,
+    E: Into<Error>,
+    H: Fn(&'static S, Request) -> F + Copy + Send,
+    F: Future<Output = Result<RowStream, ErrorResult>> + Send,
+{
+    let runtime = Builder::new_multi_thread().enable_all().build()?;
+
+    runtime.block_on(async move {
+        let state = init_state(init).await?;
+
+        run_with_streaming_response(service_fn(move |request| {
+            stream_handler(request, move |request| handler(state, request))
+        }))
+        .await
+    })
+}
+
+/// Tracing is set up before the state is created so that an init failure is logged before the
+/// function exits. The JWT verifier is set up too if `JWT_ISSUER` is configured.
+async fn init_state<S, E, I, IF>(init: I) -> Result<&'static S, Error>
+where
+    S: Sync + 'static,
+    I: FnOnce() -> IF,
+    IF: Future<Output = Result<S, E>>,
+    E: Into<Error>,
+{
+    init_tracing();
+
+    let state = async {
+        init_verifier().await?;
+        init().await.map_err(Into::into)
+    };
+
+    match state.await {
+        Ok(state) => Ok(&*Box::leak(Box::new(state))),
+        Err(err) => {
+            error!(target: "init", "Unable to initialize the function. {:?}", err);
+            flush().await;
+
+            Err(err)
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use std::{future::Future, task::Poll};
+
+use bytes::Bytes;
+use futures_util::{
+    future::ready,
+    stream::{self, BoxStream},
+    Stream, StreamExt,
+};
+use http_body::Frame;
+use http_body_util::StreamBody;
+use lambda_http::{
+    http::header::{HeaderName, CONTENT_TYPE},
+    Body, Error, Request, Response,
+};
+use model::error::{internal_server, ErrorResult};
+use serde::Serialize;
+use tracing::{error, Instrument, Span};
+
+use crate::{
+    export::{ExportFormat, CONTENT_TYPE_NDJSON},
+    json::{error_response, ResponseContext},
+    jwt::verify_alb,
+    problem::CONTENT_TYPE_JSON,
+    request::X_REQUEST_ID,
+    telemetry::flush,
+    tracing::request_span,
+};
+
+/// The frames of a streamed response.
+type Frames = BoxStream<'static, Result<Frame<Bytes>, Error>>;
+
+/// The body of a streamed response.
+pub type ResponseStream = StreamBody<Frames>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+    /// `[row,row]`, which a client can only parse once the whole array is read.
+    JsonArray,
+    /// One row per line, which a client can parse a line at a time.
+    Ndjson,
+}
+
+impl StreamFormat {
+    /// NDJSON if the `Accept` header prefers it over JSON, otherwise a JSON array.
+    pub fn negotiate(request: &Request) -> Self {
+        match ExportFormat::negotiate(request) {
+            Some(ExportFormat::Ndjson) => Self::Ndjson,
+            _ => Self::JsonArray,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::JsonArray => CONTENT_TYPE_JSON,
+            Self::Ndjson => CONTENT_TYPE_NDJSON,
+        }
+    }
+
+    fn open(&self) -> &'static [u8] {
+        match self {
+            Self::JsonArray => b"[",
+            Self::Ndjson => b"",
+        }
+    }
+
+    fn separator(&self) -> &'static [u8] {
+        match self {
+            Self::JsonArray => b",",
+            Self::Ndjson => b"",
+        }
+    }
+
+    fn terminator(&self) -> &'static [u8] {
+        match self {
+            Self::JsonArray => b"",
+            Self::Ndjson => b"\n",
+        }
+    }
+
+    fn close(&self) -> &'static [u8] {
+        match self {
+            Self::JsonArray => b"]",
+            Self::Ndjson => b"",
+        }
+    }
+}
+
+/// Rows that are serialized and sent as they are read, e.g. the rows of a `sqlx` `fetch`.
+pub struct RowStream {
+    pub format: StreamFormat,
+    pub headers: Vec<(HeaderName, String)>,
+    rows: BoxStream<'static, Result<Vec<u8>, ErrorResult>>,
+}
+
+impl RowStream {
+    pub fn new<T, S>(format: StreamFormat, rows: S) -> Self
+    where
+        T: Serialize,
+        S: Stream<Item = Result<T, ErrorResult>> + Send + 'static,
+    {
+        let rows = rows
+            .map(|row| {
+                row.and_then(|row| {
+                    serde_json::to_vec(&row).map_err(|error| {
+                        error!(target: "stream", "Error when parsing the struct. {:?}", error);
+                        internal_server()
+                    })
+                })
+            })
+            .boxed();
+
+        Self {
+            format,
+            headers: Vec::new(),
+            rows,
+        }
+    }
+
+    pub fn header(mut self, name: HeaderName, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+}
+
+/// Same as `json_handler` for a handler that streams its rows. The status is sent with the
+/// first row, so an error until then, e.g. of the query, is an `ErrorResult` as usual.
+/// An error after that ends the stream early and Lambda reports the invocation as failed.
+/// The body is read by the runtime after this returns, so it is traced in the request span
+/// and the spans are flushed once it has been sent.
+pub async fn stream_handler<H, F>(
+    request: Request,
+    handler: H,
+) -> Result<Response<ResponseStream>, Error>
+where
+    H: FnOnce(Request) -> F,
+    F: Future<Output = Result<RowStream, ErrorResult>>,
+{
+    let context = ResponseContext::new(&request);
+    let span = request_span(&request, context.request_id.as_deref());
+    let response = async move {
+        let response = match handler(verify_alb(request).await).await {
+            Ok(rows) => stream_response(rows, &context).await,
+            Err(error) => buffered(error_response(error, &context)),
+        };
+
+        if let Ok(response) = &response {
+            Span::current().record("http.response.status_code", response.status().as_u16());
+        }
+
+        response
+    }
+    .instrument(span.clone())
+    .await;
+
+    match response {
+        Ok(response) => Ok(response.map(|body| StreamBody::new(traced(body, span)))),
+        Err(error) => {
+            drop(span);
+            flush().await;
+
+            Err(error)
+        }
+    }
+}
+
+/// The `body` polled in the request `span`. The span ends with the last frame, so that it is
+/// exported by the flush that follows.
+fn traced(mut body: Frames, span: Span) -> Frames {
+    let mut span = Some(span);
+    let frames = stream::poll_fn(move |cx| {
+        let Some(current) = &span else {
+            return Poll::Ready(None);
+        };
+        let frame = current.in_scope(|| body.poll_next_unpin(cx));
+
+        if let Poll::Ready(None) = frame {
+            span = None;
+        }
+
+        frame
+    });
+
+    frames
+        .chain(stream::once(flush()).filter_map(|()| ready(None)))
+        .boxed()
+}
+
+async fn stream_response(
+    rows: RowStream,
+    context: &ResponseContext,
+) -> Result<Response<Frames>, Error> {
+    let RowStream {
+        format,
+        headers,
+        mut rows,
+    } = rows;
+
+    let first = match rows.next().await {
+        Some(Err(error)) => return buffered(error_response(error, context)),
+        first => first,
+    };
+
+    let mut builder = Response::builder()
+        .status(200)
+        .header(CONTENT_TYPE, format.content_type());
+
+    for (name, value) in headers {
+        builder = builder.header(name, value);
+    }
+
+    if let Some(request_id) = &context.request_id {
+        builder = builder.header(X_REQUEST_ID, request_id);
+    }
+
+    let rows = stream::iter(first)
+        .chain(rows)
+        .enumerate()
+        .map(move |(index, row)| {
+            let mut chunk = if index == 0 {
+                Vec::new()
+            } else {
+                format.separator().to_vec()
+            };
+            chunk.extend(row?);
+            chunk.extend(format.terminator());
+
+            Ok(chunk)
+        });
+    let body = stream::once(ready(Ok(format.open().to_vec())))
+        .chain(rows)
+        .chain(stream::once(ready(Ok(format.close().to_vec()))))
+        .scan(false, |failed, chunk: Result<Vec<u8>, ErrorResult>| {
+            if *failed {
+                return ready(None);
+            }
+
+            let frame = chunk.map(|chunk| Frame::data(Bytes::from(chunk)));
+            ready(Some(frame.map_err(|error| {
+                *failed = true;
+                error!(target: "stream", "Error when streaming the rows. {:?}", error);
+                Error::from("Error when streaming the rows.")
+            })))
+        })
+        .boxed();
+
+    builder.body(body).map(Ok)?
+}
+
+/// A response of `json_handler` as a stream of a single chunk.
+fn buffered(response: Result<Response<Body>, Error>) -> Result<Response<Frames>, Error> {
+    response.map(|response| {
+        response.map(|body| {
+            let bytes = match body {
+                Body::Empty => Bytes::new(),
+                Body::Text(text) => Bytes::from(text),
+                Body::Binary(binary) => Bytes::from(binary),
+            };
+
+            stream::once(ready(Ok(Frame::data(bytes)))).boxed()
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use futures_util::stream;
+    use http_body_util::BodyExt;
+    use lambda_http::{http::header::CONTENT_TYPE, Request};
+    use model::error::{internal_server, ErrorResult};
+    use serde_json::{json, Value};
+    use tracing::Span;
+
+    use super::{stream_handler, RowStream, StreamFormat};
+
+    async fn body(format: StreamFormat, rows: Vec<Result<Value, ErrorResult>>) -> (u16, String) {
+        let response = stream_handler(Request::default(), |_| async move {
+            Ok(RowStream::new(format, stream::iter(rows)))
+        })
+        .await
+        .unwrap();
+        let status = response.status().as_u16();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn stream_handler_should_frame_rows() {
+        let rows = || vec![Ok(json!({ "id": 1 })), Ok(json!({ "id": 2 }))];
+
+        assert_eq!(
+            body(StreamFormat::JsonArray, rows()).await,
+            (200, r#"[{"id":1},{"id":2}]"#.to_owned())
+        );
+        assert_eq!(
+            body(StreamFormat::Ndjson, rows()).await,
+            (200, "{\"id\":1}\n{\"id\":2}\n".to_owned())
+        );
+        assert_eq!(
+            body(StreamFormat::JsonArray, Vec::new()).await,
+            (200, "[]".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_handler_error_before_first_row_should_return_error() {
+        let response = stream_handler(Request::default(), |_| async {
+            Ok(RowStream::new(
+                StreamFormat::Ndjson,
+                stream::iter(vec![Err::<Value, _>(internal_server())]),
+            ))
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), 500);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+    }
+
+    #[tokio::test]
+    async fn stream_handler_error_after_first_row_should_end_stream() {
+        let rows = vec![Ok(json!({ "id": 1 })), Err(internal_server()), Ok(json!(2))];
+        let response = stream_handler(Request::default(), |_| async move {
+            Ok(RowStream::new(StreamFormat::JsonArray, stream::iter(rows)))
+        })
+        .await
+        .unwrap();
+
+        assert!(response.into_body().collect().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn stream_handler_should_read_rows_in_request_span() {
+        let _subscriber = tracing::subscriber::set_default(tracing_subscriber::fmt().finish());
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        let polled = spans.clone();
+        // The iterator is lazy, so each row is made when the stream is polled.
+        let rows = stream::iter((1..=3).map(move |row| {
+            let span = Span::current().metadata().map(|metadata| metadata.name());
+            polled.lock().unwrap().push(span);
+
+            Ok::<_, ErrorResult>(json!(row))
+        }));
+        let response = stream_handler(Request::default(), |_| async move {
+            Ok(RowStream::new(StreamFormat::Ndjson, rows))
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(spans.lock().unwrap().len(), 1);
+
+        response.into_body().collect().await.unwrap();
+
+        assert_eq!(*spans.lock().unwrap(), vec![Some("request"); 3]);
+    }
+}
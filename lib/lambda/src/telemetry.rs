@@ -0,0 +1,311 @@
+use std::{env, sync::OnceLock};
+
+use lambda_http::{Request, RequestExt};
+use opentelemetry::{
+    propagation::{text_map_propagator::FieldIter, Extractor, Injector, TextMapPropagator},
+    trace::{
+        SpanContext, SpanId, TraceContextExt, TraceError, TraceFlags, TraceId, TraceState,
+        TracerProvider as _,
+    },
+    Context, KeyValue,
+};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    runtime,
+    trace::{Tracer, TracerProvider},
+    Resource,
+};
+use tracing::error;
+
+pub const X_AMZN_TRACE_ID: &str = "x-amzn-trace-id";
+const ENV_TRACE_ID: &str = "_X_AMZN_TRACE_ID";
+
+static PROVIDER: OnceLock<TracerProvider> = OnceLock::new();
+static FIELDS: OnceLock<[String; 1]> = OnceLock::new();
+
+/// Creates the tracer that exports spans through OTLP over HTTP. This is only enabled when an
+/// OTLP endpoint is configured via `OTEL_EXPORTER_OTLP_ENDPOINT` or
+/// `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`, e.g. the collector extension at `http://localhost:4318`.
+pub fn init_tracer() -> Result<Option<Tracer>, TraceError> {
+    let enabled = [
+        "OTEL_EXPORTER_OTLP_ENDPOINT",
+        "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+    ]
+    .into_iter()
+    .any(|key| env::var_os(key).is_some());
+
+    if !enabled {
+        return Ok(None);
+    }
+
+    let provider = tracer_provider(None)?;
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+
+    opentelemetry::global::set_text_map_propagator(XRayPropagator);
+    PROVIDER.get_or_init(|| provider);
+
+    Ok(Some(tracer))
+}
+
+/// The endpoint is the full URL of the traces path. When it is not given, the endpoint is
+/// resolved from the `OTEL_EXPORTER_OTLP_*` environment variables.
+pub fn tracer_provider(endpoint: Option<String>) -> Result<TracerProvider, TraceError> {
+    let builder = SpanExporter::builder().with_http();
+    let exporter = match endpoint {
+        Some(endpoint) => builder.with_endpoint(endpoint).build()?,
+        None => builder.build()?,
+    };
+
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(resource())
+        .build())
+}
+
+/// Exports the spans of the invocation before Lambda freezes the execution environment.
+pub async fn flush() {
+    let provider = match PROVIDER.get() {
+        Some(provider) => provider.clone(),
+        None => return,
+    };
+    let results = tokio::task::spawn_blocking(move || provider.force_flush()).await;
+
+    for result in results.into_iter().flatten() {
+        if let Err(err) = result {
+            error!(target: "flush", "Unable to export the spans. {:?}", err);
+        }
+    }
+}
+
+/// The trace context of the invocation. The Lambda context has the trace id of the function's
+/// own segment, otherwise it's taken from the `X-Amzn-Trace-Id` header or the environment.
+pub fn parent_context(request: &Request) -> Context {
+    let trace_id = request
+        .lambda_context_ref()
+        .and_then(|context| context.xray_trace_id.to_owned())
+        .or_else(|| {
+            request
+                .headers()
+                .get(X_AMZN_TRACE_ID)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        })
+        .or_else(|| env::var(ENV_TRACE_ID).ok());
+
+    match trace_id {
+        Some(trace_id) => XRayPropagator.extract(&TraceHeader(trace_id)),
+        None => Context::new(),
+    }
+}
+
+fn resource() -> Resource {
+    let name = env::var("OTEL_SERVICE_NAME")
+        .or_else(|_| env::var("AWS_LAMBDA_FUNCTION_NAME"))
+        .unwrap_or_else(|_| env!("CARGO_PKG_NAME").to_owned());
+    let version = env::var("AWS_LAMBDA_FUNCTION_VERSION").unwrap_or_default();
+
+    Resource::new([
+        KeyValue::new("service.name", name),
+        KeyValue::new("cloud.provider", "aws"),
+        KeyValue::new("faas.version", version),
+    ])
+}
+
+struct TraceHeader(String);
+
+impl Extractor for TraceHeader {
+    fn get(&self, key: &str) -> Option<&str> {
+        key.eq_ignore_ascii_case(X_AMZN_TRACE_ID)
+            .then_some(self.0.as_str())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        vec![X_AMZN_TRACE_ID]
+    }
+}
+
+/// Propagates the trace context in the AWS X-Ray format, e.g.
+/// `Root=1-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=1`
+#[derive(Debug, Default)]
+pub struct XRayPropagator;
+
+impl XRayPropagator {
+    fn span_context(header: &str) -> Option<SpanContext> {
+        let mut trace_id = None;
+        let mut span_id = None;
+        let mut flags = TraceFlags::default();
+
+        for part in header.split(';').map(str::trim) {
+            let Some(pair) = part.split_once('=') else {
+                continue;
+            };
+
+            match pair {
+                ("Root", root) => {
+                    let (version, id) = root.split_once('-')?;
+                    if version != "1" {
+                        return None;
+                    }
+                    trace_id = TraceId::from_hex(&id.replace('-', "")).ok();
+                }
+                ("Parent", parent) => span_id = SpanId::from_hex(parent).ok(),
+                ("Sampled", "1") => flags = TraceFlags::SAMPLED,
+                _ => {}
+            }
+        }
+
+        let context = SpanContext::new(trace_id?, span_id?, flags, true, TraceState::default());
+
+        context.is_valid().then_some(context)
+    }
+}
+
+impl TextMapPropagator for XRayPropagator {
+    fn inject_context(&self, cx: &Context, injector: &mut dyn Injector) {
+        let span = cx.span();
+        let context = span.span_context();
+
+        if !context.is_valid() {
+            return;
+        }
+
+        let trace_id = context.trace_id().to_string();
+        let (epoch, unique) = trace_id.split_at(8);
+        let sampled = if context.is_sampled() { 1 } else { 0 };
+        let header = format!(
+            "Root=1-{epoch}-{unique};Parent={};Sampled={sampled}",
+            context.span_id()
+        );
+
+        injector.set(X_AMZN_TRACE_ID, header);
+    }
+
+    fn extract_with_context(&self, cx: &Context, extractor: &dyn Extractor) -> Context {
+        extractor
+            .get(X_AMZN_TRACE_ID)
+            .and_then(Self::span_context)
+            .map(|context| cx.with_remote_span_context(context))
+            .unwrap_or_else(|| cx.clone())
+    }
+
+    fn fields(&self) -> FieldIter<'_> {
+        FieldIter::new(FIELDS.get_or_init(|| [X_AMZN_TRACE_ID.to_owned()]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::mpsc,
+        thread,
+        time::Duration,
+    };
+
+    use opentelemetry::{
+        propagation::TextMapPropagator,
+        trace::{Span, TraceContextExt, Tracer, TracerProvider},
+    };
+
+    use super::{tracer_provider, XRayPropagator, X_AMZN_TRACE_ID};
+
+    const HEADER: &str =
+        "Root=1-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=1";
+
+    #[test]
+    fn extract_should_return_remote_context() {
+        let carrier = HashMap::from([(X_AMZN_TRACE_ID.to_owned(), HEADER.to_owned())]);
+        let context = XRayPropagator.extract(&carrier);
+        let span = context.span();
+        let span_context = span.span_context();
+
+        assert!(span_context.is_remote());
+        assert!(span_context.is_sampled());
+        assert_eq!(
+            span_context.trace_id().to_string(),
+            "5759e988bd862e3fe1be46a994272793"
+        );
+        assert_eq!(span_context.span_id().to_string(), "53995c3f42cd8ad8");
+    }
+
+    #[test]
+    fn extract_invalid_should_return_empty_context() {
+        let carrier = HashMap::from([(X_AMZN_TRACE_ID.to_owned(), "Root=2-abc".to_owned())]);
+        let context = XRayPropagator.extract(&carrier);
+
+        assert!(!context.span().span_context().is_valid());
+    }
+
+    #[test]
+    fn inject_should_write_header() {
+        let carrier = HashMap::from([(X_AMZN_TRACE_ID.to_owned(), HEADER.to_owned())]);
+        let context = XRayPropagator.extract(&carrier);
+        let mut injected = HashMap::<String, String>::new();
+        XRayPropagator.inject_context(&context, &mut injected);
+
+        assert_eq!(injected[X_AMZN_TRACE_ID], HEADER);
+    }
+
+    /// Accepts a single OTLP request and sends back its request line.
+    fn collector() -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/v1/traces", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            let mut length = 0;
+            reader.read_line(&mut request_line).unwrap();
+
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim().to_lowercase();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(value) = line.strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .unwrap();
+            sender
+                .send(format!("{} {}", request_line.trim(), body.len()))
+                .unwrap();
+        });
+
+        (endpoint, receiver)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn flush_should_export_to_collector() {
+        let (endpoint, receiver) = collector();
+        let provider = tracer_provider(Some(endpoint)).unwrap();
+        let tracer = provider.tracer("test");
+        let carrier = HashMap::from([(X_AMZN_TRACE_ID.to_owned(), HEADER.to_owned())]);
+        let parent = XRayPropagator.extract(&carrier);
+        let mut span = tracer.start_with_context("query", &parent);
+        span.end();
+
+        let provider_flush = provider.clone();
+        tokio::task::spawn_blocking(move || provider_flush.force_flush())
+            .await
+            .unwrap();
+        let received = receiver
+            .recv_timeout(Duration::from_secs(10))
+            .expect("the collector received no export");
+
+        assert!(received.starts_with("POST /v1/traces HTTP/1.1"));
+        assert!(!received.ends_with(" 0"));
+    }
+}
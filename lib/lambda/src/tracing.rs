@@ -1,9 +1,175 @@
-use tracing::Level;
+use std::{
+    env,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
+use lambda_http::Request;
+use tracing::{error, field, info_span, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+use crate::telemetry::{init_tracer, parent_context};
+
+const LEVEL_DEFAULT: &str = "info";
+
+static COLD_START: AtomicBool = AtomicBool::new(true);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    /// `LOG_FORMAT` takes precedence over the format configured in the Lambda logging settings.
+    pub fn from_env() -> Self {
+        let format = env::var("LOG_FORMAT")
+            .or_else(|_| env::var("AWS_LAMBDA_LOG_FORMAT"))
+            .unwrap_or_default();
+
+        Self::parse(&format)
+    }
+
+    fn parse(value: &str) -> Self {
+        if value.eq_ignore_ascii_case("json") {
+            Self::Json
+        } else {
+            Self::Text
+        }
+    }
+}
+
+/// Reads the filter from `RUST_LOG` (e.g. `info,sqlx=warn`), then from the Lambda log level,
+/// and the format from `LOG_FORMAT`, so both can be changed per stage without rebuilding.
+/// Spans are also exported through OTLP when an endpoint is configured.
 pub fn init_tracing() {
-    tracing_subscriber::fmt()
-        .with_max_level(Level::INFO)
-        .with_target(false)
-        .without_time()
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| {
+            env::var("AWS_LAMBDA_LOG_LEVEL").map(|level| EnvFilter::new(level.to_lowercase()))
+        })
+        .unwrap_or_else(|_| EnvFilter::new(LEVEL_DEFAULT));
+    let format = LogFormat::from_env();
+    let json = (format == LogFormat::Json).then(|| {
+        fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .with_target(false)
+    });
+    let text = (format == LogFormat::Text).then(|| fmt::layer().with_target(false).without_time());
+    let tracer = init_tracer();
+    let telemetry = tracer
+        .as_ref()
+        .ok()
+        .and_then(Option::clone)
+        .map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(json)
+        .with(text)
+        .with(telemetry)
         .init();
+
+    if let Err(err) = tracer {
+        error!(target: "init_tracing", "Unable to create the OTLP exporter. {:?}", err);
+    }
+}
+
+/// The span of a single invocation. Every record logged within it carries these fields.
+/// `user_id` is recorded once the caller is authenticated and `http.response.status_code`
+/// once the response is built. The span continues the trace of the caller if there is one.
+pub fn request_span(request: &Request, request_id: Option<&str>) -> Span {
+    let cold_start = COLD_START.swap(false, Ordering::Relaxed);
+    let span = info_span!(
+        "request",
+        otel.kind = "server",
+        http.request.method = request.method().as_str(),
+        url.path = request.uri().path(),
+        http.response.status_code = field::Empty,
+        request_id = request_id.unwrap_or_default(),
+        function_name = env::var("AWS_LAMBDA_FUNCTION_NAME").unwrap_or_default(),
+        function_version = env::var("AWS_LAMBDA_FUNCTION_VERSION").unwrap_or_default(),
+        cold_start,
+        user_id = field::Empty,
+    );
+    span.set_parent(parent_context(request));
+
+    span
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        sync::{Arc, Mutex},
+    };
+
+    use lambda_http::Request;
+    use serde_json::Value;
+    use tracing::{info, Span};
+    use tracing_subscriber::fmt::MakeWriter;
+
+    use super::{request_span, LogFormat};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Buffer;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn parse_should_return_format() {
+        assert_eq!(LogFormat::parse("JSON"), LogFormat::Json);
+        assert_eq!(LogFormat::parse("Text"), LogFormat::Text);
+        assert_eq!(LogFormat::parse(""), LogFormat::Text);
+    }
+
+    #[test]
+    fn json_record_should_contain_request_fields() {
+        let buffer = Buffer::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(buffer.clone())
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let request = Request::default();
+            let first = request_span(&request, Some("abc"));
+            let second = request_span(&request, Some("def"));
+            let _enter = second.enter();
+
+            Span::current().record("user_id", "user-1");
+            info!("Handled");
+            drop(first);
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let record = serde_json::from_str::<Value>(output.trim()).unwrap();
+
+        assert_eq!(record["message"], "Handled");
+        assert_eq!(record["span"]["request_id"], "def");
+        assert_eq!(record["span"]["user_id"], "user-1");
+        assert_eq!(record["span"]["cold_start"], false);
+        assert!(record["timestamp"].is_string());
+    }
 }
@@ -1,20 +1,166 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap, fmt};
 
-use serde::Serialize;
+use serde::{Serialize, Serializer};
 use serde_json::Value;
 use serde_with::skip_serializing_none;
 
+/// The catalog of error codes. Each code has exactly one HTTP status so that the status of an
+/// `ErrorResult` is always derived from its codes instead of being written by hand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorCode {
+    Duplicate,
+    Forbidden,
+    IdempotencyKeyReused,
+    Invalid,
+    NotFound,
+    PayloadTooLarge,
+    PreconditionRequired,
+    ReferenceNotFound,
+    Referenced,
+    Required,
+    ServerInternal,
+    Unauthorized,
+    UnsupportedMediaType,
+    VersionConflict,
+    /// Codes that are not part of this catalog, such as the codes returned by `validator`
+    /// or the codes defined by domain crates via `ErrorCode::custom`.
+    Custom {
+        code: Cow<'static, str>,
+        status: u16,
+    },
+}
+
+impl ErrorCode {
+    /// Defines a domain specific code, e.g.
+    /// `pub const SOLD_OUT: ErrorCode = ErrorCode::custom("sold_out", 409);`
+    pub const fn custom(code: &'static str, status: u16) -> Self {
+        Self::Custom {
+            code: Cow::Borrowed(code),
+            status,
+        }
+    }
+
+    /// A code coming from `validator` is the code of the catalog with the same name, e.g.
+    /// `duplicate` is a `409`. Any other code is a bad request.
+    pub fn validation(code: Cow<'static, str>) -> Self {
+        Self::from_name(&code).unwrap_or(Self::Custom { code, status: 400 })
+    }
+
+    /// The code of the catalog with the name, which is the inverse of `as_str`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let code = match name {
+            "duplicate" => Self::Duplicate,
+            "forbidden" => Self::Forbidden,
+            "idempotency_key_reused" => Self::IdempotencyKeyReused,
+            "invalid" => Self::Invalid,
+            "not_found" => Self::NotFound,
+            "payload_too_large" => Self::PayloadTooLarge,
+            "precondition_required" => Self::PreconditionRequired,
+            "reference_not_found" => Self::ReferenceNotFound,
+            "referenced" => Self::Referenced,
+            "required" => Self::Required,
+            "server_internal" => Self::ServerInternal,
+            "unauthorized" => Self::Unauthorized,
+            "unsupported_media_type" => Self::UnsupportedMediaType,
+            "version_conflict" => Self::VersionConflict,
+            _ => return None,
+        };
+
+        Some(code)
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Duplicate => "duplicate",
+            Self::Forbidden => "forbidden",
+            Self::IdempotencyKeyReused => "idempotency_key_reused",
+            Self::Invalid => "invalid",
+            Self::NotFound => "not_found",
+            Self::PayloadTooLarge => "payload_too_large",
+            Self::PreconditionRequired => "precondition_required",
+            Self::ReferenceNotFound => "reference_not_found",
+            Self::Referenced => "referenced",
+            Self::Required => "required",
+            Self::ServerInternal => "server_internal",
+            Self::Unauthorized => "unauthorized",
+            Self::UnsupportedMediaType => "unsupported_media_type",
+            Self::VersionConflict => "version_conflict",
+            Self::Custom { code, .. } => code,
+        }
+    }
+
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::Invalid | Self::Required => 400,
+            Self::Unauthorized => 401,
+            Self::Forbidden => 403,
+            Self::NotFound => 404,
+            Self::Duplicate | Self::ReferenceNotFound | Self::Referenced => 409,
+            Self::VersionConflict => 412,
+            Self::PayloadTooLarge => 413,
+            Self::UnsupportedMediaType => 415,
+            Self::IdempotencyKeyReused => 422,
+            Self::PreconditionRequired => 428,
+            Self::ServerInternal => 500,
+            Self::Custom { status, .. } => *status,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct ErrorResult {
-    pub status: u16,
+    status: u16,
     pub errors: Vec<ErrorDetail>,
+    /// Correlation id of the request so that server errors can be traced back to the logs.
+    #[serde(rename = "requestId", skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl ErrorResult {
+    /// The status is the highest status of all the error codes.
+    pub fn new(errors: Vec<ErrorDetail>) -> Self {
+        let status = errors
+            .iter()
+            .map(|error| error.code.status())
+            .max()
+            .unwrap_or(500);
+
+        Self {
+            status,
+            errors,
+            request_id: None,
+        }
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+}
+
+impl From<ErrorDetail> for ErrorResult {
+    fn from(error: ErrorDetail) -> Self {
+        Self::new(vec![error])
+    }
 }
 
 #[skip_serializing_none]
 #[derive(Debug, Serialize)]
 pub struct ErrorDetail {
     pub id: Option<Value>,
-    pub code: String,
+    pub code: ErrorCode,
+    pub message: Option<String>,
     pub source: ErrorSource,
 }
 
@@ -30,7 +176,8 @@ pub struct ErrorSource {
 pub fn internal_server() -> ErrorResult {
     let error = ErrorDetail {
         id: None,
-        code: "server_internal".to_owned(),
+        code: ErrorCode::ServerInternal,
+        message: None,
         source: ErrorSource {
             pointer: Some("/server".to_owned()),
             header: None,
@@ -39,16 +186,14 @@ pub fn internal_server() -> ErrorResult {
         },
     };
 
-    ErrorResult {
-        status: 500,
-        errors: vec![error],
-    }
+    ErrorResult::from(error)
 }
 
 pub fn unauthorized() -> ErrorResult {
     let error = ErrorDetail {
         id: None,
-        code: "unauthorized".to_owned(),
+        code: ErrorCode::Unauthorized,
+        message: None,
         source: ErrorSource {
             pointer: None,
             header: Some("authorization".to_owned()),
@@ -57,16 +202,31 @@ pub fn unauthorized() -> ErrorResult {
         },
     };
 
-    ErrorResult {
-        status: 500,
-        errors: vec![error],
-    }
+    ErrorResult::from(error)
+}
+
+/// The caller is authenticated but is missing the `role` or `scope` in `meta`.
+pub fn forbidden(meta: HashMap<String, Value>) -> ErrorResult {
+    let error = ErrorDetail {
+        id: None,
+        code: ErrorCode::Forbidden,
+        message: None,
+        source: ErrorSource {
+            pointer: None,
+            header: Some("authorization".to_owned()),
+            parameter: None,
+            meta: Some(meta),
+        },
+    };
+
+    ErrorResult::from(error)
 }
 
 pub fn required_body() -> ErrorResult {
     let error = ErrorDetail {
         id: None,
-        code: "required".to_owned(),
+        code: ErrorCode::Required,
+        message: None,
         source: ErrorSource {
             pointer: Some("/body".to_owned()),
             header: None,
@@ -75,16 +235,14 @@ pub fn required_body() -> ErrorResult {
         },
     };
 
-    ErrorResult {
-        status: 400,
-        errors: vec![error],
-    }
+    ErrorResult::from(error)
 }
 
 pub fn required_parameter(name: &str) -> ErrorResult {
     let error = ErrorDetail {
         id: None,
-        code: "required".to_owned(),
+        code: ErrorCode::Required,
+        message: None,
         source: ErrorSource {
             pointer: None,
             header: None,
@@ -93,16 +251,14 @@ pub fn required_parameter(name: &str) -> ErrorResult {
         },
     };
 
-    ErrorResult {
-        status: 400,
-        errors: vec![error],
-    }
+    ErrorResult::from(error)
 }
 
 pub fn invalid_parameter(name: String) -> ErrorResult {
     let error = ErrorDetail {
         id: None,
-        code: "invalid".to_owned(),
+        code: ErrorCode::Invalid,
+        message: None,
         source: ErrorSource {
             pointer: None,
             header: None,
@@ -111,17 +267,121 @@ pub fn invalid_parameter(name: String) -> ErrorResult {
         },
     };
 
-    ErrorResult {
-        status: 400,
-        errors: vec![error],
+    ErrorResult::from(error)
+}
+
+/// A value of the body that is not valid, e.g. `/body/translations/2/ordinal` with the expected
+/// `type` in `meta`.
+pub fn invalid_field(pointer: String, meta: HashMap<String, Value>) -> ErrorResult {
+    let error = ErrorDetail {
+        id: None,
+        code: ErrorCode::Invalid,
+        message: None,
+        source: ErrorSource {
+            pointer: Some(pointer),
+            header: None,
+            parameter: None,
+            meta: (!meta.is_empty()).then_some(meta),
+        },
+    };
+
+    ErrorResult::from(error)
+}
+
+pub fn required_field(pointer: String) -> ErrorResult {
+    let error = ErrorDetail {
+        id: None,
+        code: ErrorCode::Required,
+        message: None,
+        source: ErrorSource {
+            pointer: Some(pointer),
+            header: None,
+            parameter: None,
+            meta: None,
+        },
+    };
+
+    ErrorResult::from(error)
+}
+
+/// The body is larger than `max` bytes.
+pub fn payload_too_large(max: usize) -> ErrorResult {
+    let meta = HashMap::from([("max".to_owned(), Value::from(max))]);
+    let error = ErrorDetail {
+        id: None,
+        code: ErrorCode::PayloadTooLarge,
+        message: None,
+        source: ErrorSource {
+            pointer: Some("/body".to_owned()),
+            header: None,
+            parameter: None,
+            meta: Some(meta),
+        },
+    };
+
+    ErrorResult::from(error)
+}
+
+/// An export above `max` bytes. The `url` streams it without a limit.
+pub fn export_too_large(max: usize, url: Option<&str>) -> ErrorResult {
+    let mut meta = HashMap::from([("max".to_owned(), Value::from(max))]);
+    if let Some(url) = url {
+        meta.insert("url".to_owned(), Value::from(url));
     }
+    let error = ErrorDetail {
+        id: None,
+        code: ErrorCode::PayloadTooLarge,
+        message: None,
+        source: ErrorSource {
+            pointer: None,
+            header: None,
+            parameter: None,
+            meta: Some(meta),
+        },
+    };
+
+    ErrorResult::from(error)
+}
+
+pub fn invalid_header(name: &str) -> ErrorResult {
+    let error = ErrorDetail {
+        id: None,
+        code: ErrorCode::Invalid,
+        message: None,
+        source: ErrorSource {
+            pointer: None,
+            header: Some(name.to_owned()),
+            parameter: None,
+            meta: None,
+        },
+    };
+
+    ErrorResult::from(error)
+}
+
+/// The `Idempotency-Key` was already used for a request with a different payload.
+pub fn idempotency_key_reused() -> ErrorResult {
+    let error = ErrorDetail {
+        id: None,
+        code: ErrorCode::IdempotencyKeyReused,
+        message: None,
+        source: ErrorSource {
+            pointer: None,
+            header: Some("idempotency-key".to_owned()),
+            parameter: None,
+            meta: None,
+        },
+    };
+
+    ErrorResult::from(error)
 }
 
 pub fn id_not_found(entity: &str, id: i64) -> ErrorResult {
     let pointer = format!("/data/{entity}/id");
     let error = ErrorDetail {
         id: Some(Value::from(id)),
-        code: "not_found".to_owned(),
+        code: ErrorCode::NotFound,
+        message: None,
         source: ErrorSource {
             pointer: Some(pointer),
             parameter: None,
@@ -130,36 +390,68 @@ pub fn id_not_found(entity: &str, id: i64) -> ErrorResult {
         },
     };
 
-    ErrorResult {
-        status: 404,
-        errors: vec![error],
-    }
+    ErrorResult::from(error)
 }
 
+/// The `If-Match` version of the record is no longer the current version.
 pub fn version_conflict(entity: &str, id: i64, version: i16) -> ErrorResult {
     let pointer = format!("/data/{entity}/version");
     let meta = HashMap::from([("version".to_owned(), Value::from(version))]);
     let error = ErrorDetail {
         id: Some(Value::from(id)),
-        code: "version_conflict".to_owned(),
+        code: ErrorCode::VersionConflict,
+        message: None,
         source: ErrorSource {
             pointer: Some(pointer),
             parameter: None,
-            header: None,
+            header: Some("if-match".to_owned()),
             meta: Some(meta),
         },
     };
 
-    ErrorResult {
-        status: 409,
-        errors: vec![error],
-    }
+    ErrorResult::from(error)
+}
+
+/// Changing a record requires the `If-Match` header with the `ETag` of its current version.
+pub fn precondition_required() -> ErrorResult {
+    let error = ErrorDetail {
+        id: None,
+        code: ErrorCode::PreconditionRequired,
+        message: None,
+        source: ErrorSource {
+            pointer: None,
+            parameter: None,
+            header: Some("if-match".to_owned()),
+            meta: None,
+        },
+    };
+
+    ErrorResult::from(error)
+}
+
+/// The `Content-Type` of the body is not the `expected` one.
+pub fn unsupported_media_type(expected: &str) -> ErrorResult {
+    let meta = HashMap::from([("expected".to_owned(), Value::from(expected))]);
+    let error = ErrorDetail {
+        id: None,
+        code: ErrorCode::UnsupportedMediaType,
+        message: None,
+        source: ErrorSource {
+            pointer: None,
+            parameter: None,
+            header: Some("content-type".to_owned()),
+            meta: Some(meta),
+        },
+    };
+
+    ErrorResult::from(error)
 }
 
 pub fn path_not_found() -> ErrorResult {
     let error = ErrorDetail {
         id: None,
-        code: "not_found".to_owned(),
+        code: ErrorCode::NotFound,
+        message: None,
         source: ErrorSource {
             pointer: Some("/path".to_owned()),
             parameter: None,
@@ -168,16 +460,14 @@ pub fn path_not_found() -> ErrorResult {
         },
     };
 
-    ErrorResult {
-        status: 404,
-        errors: vec![error],
-    }
+    ErrorResult::from(error)
 }
 
 pub fn invalid_body() -> ErrorResult {
     let error = ErrorDetail {
         id: None,
-        code: "invalid".to_owned(),
+        code: ErrorCode::Invalid,
+        message: None,
         source: ErrorSource {
             pointer: Some("/body".to_owned()),
             header: None,
@@ -186,8 +476,75 @@ pub fn invalid_body() -> ErrorResult {
         },
     };
 
-    ErrorResult {
-        status: 400,
-        errors: vec![error],
+    ErrorResult::from(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{unauthorized, ErrorCode, ErrorDetail, ErrorResult, ErrorSource};
+    use serde_json::json;
+
+    const SOLD_OUT: ErrorCode = ErrorCode::custom("sold_out", 410);
+
+    fn detail(code: ErrorCode) -> ErrorDetail {
+        ErrorDetail {
+            id: None,
+            code,
+            message: None,
+            source: ErrorSource {
+                pointer: None,
+                parameter: None,
+                header: None,
+                meta: None,
+            },
+        }
+    }
+
+    #[test]
+    fn unauthorized_should_return_401() {
+        assert_eq!(unauthorized().status(), 401);
+    }
+
+    #[test]
+    fn code_should_serialize_to_wire_string() {
+        let value = serde_json::to_value(detail(ErrorCode::VersionConflict)).unwrap();
+
+        assert_eq!(value, json!({ "code": "version_conflict", "source": {} }));
+    }
+
+    #[test]
+    fn custom_code_should_keep_status() {
+        let result = ErrorResult::from(detail(SOLD_OUT));
+        let value = serde_json::to_value(&result).unwrap();
+
+        assert_eq!(result.status(), 410);
+        assert_eq!(value["errors"][0]["code"], "sold_out");
+    }
+
+    #[test]
+    fn validation_code_should_return_catalog_code_or_400() {
+        let code = ErrorCode::validation("length".into());
+
+        assert_eq!(code.as_str(), "length");
+        assert_eq!(code.status(), 400);
+        assert_eq!(
+            ErrorCode::validation("required".into()),
+            ErrorCode::Required
+        );
+        assert_eq!(
+            ErrorCode::validation("duplicate".into()).status(),
+            ErrorCode::Duplicate.status()
+        );
+    }
+
+    #[test]
+    fn result_should_return_highest_status() {
+        let result = ErrorResult::new(vec![
+            detail(ErrorCode::Required),
+            detail(ErrorCode::Duplicate),
+        ]);
+
+        assert_eq!(result.status(), 409);
+        assert_eq!(ErrorResult::new(vec![]).status(), 500);
     }
 }
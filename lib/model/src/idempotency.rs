@@ -0,0 +1,27 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// The `Idempotency-Key` of a request and the fingerprint of its payload, which must be the
+/// same when the key is used again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdempotencyKey {
+    pub key: String,
+    pub fingerprint: String,
+}
+
+/// The result of a request that may have been made before. A replay is the stored response.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Idempotent<T> {
+    Fresh(T),
+    Replayed(Replay),
+}
+
+/// The stored response of a key, which is sent again with the status it was first sent with.
+#[derive(Debug, Serialize)]
+#[serde(transparent)]
+pub struct Replay {
+    #[serde(skip)]
+    pub status: u16,
+    pub body: Value,
+}
@@ -1,5 +1,9 @@
 pub mod error;
+pub mod idempotency;
+pub mod limit;
+pub mod message;
 pub mod page;
+pub mod precondition;
 pub mod seek;
 pub mod serde;
 pub mod translation;
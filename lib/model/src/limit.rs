@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{invalid_parameter, ErrorResult};
+
+/// The page sizes an endpoint accepts. `default` is used when the client does not ask for a size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SizeLimit {
+    pub default: i16,
+    pub min: i16,
+    pub max: i16,
+}
+
+impl SizeLimit {
+    pub const DEFAULT: Self = Self::new(20, 1, 100);
+
+    /// Panics at compile time when used in a `const` and the limits are not in order. `max` is
+    /// below `i16::MAX`, because a seek reads one row more than the size.
+    pub const fn new(default: i16, min: i16, max: i16) -> Self {
+        assert!(min >= 1 && min <= default && default <= max && max < i16::MAX);
+
+        Self { default, min, max }
+    }
+
+    /// The size to use, or an error for the `size` parameter when it is out of range.
+    pub fn size(&self, size: Option<i16>) -> Result<i16, ErrorResult> {
+        let size = size.unwrap_or(self.default);
+
+        if (self.min..=self.max).contains(&size) {
+            return Ok(size);
+        }
+
+        let mut result = invalid_parameter("size".to_owned());
+        result.errors[0].source.meta = Some(HashMap::from([
+            ("min".to_owned(), Value::from(self.min)),
+            ("max".to_owned(), Value::from(self.max)),
+        ]));
+
+        Err(result)
+    }
+}
+
+impl Default for SizeLimit {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SizeLimit;
+
+    const LIMIT: SizeLimit = SizeLimit::new(10, 5, 50);
+
+    #[test]
+    fn size_should_return_default() {
+        assert_eq!(LIMIT.size(None).unwrap(), 10);
+        assert_eq!(LIMIT.size(Some(50)).unwrap(), 50);
+    }
+
+    #[test]
+    fn size_out_of_range_should_return_invalid_parameter() {
+        for size in [4, 51, -1] {
+            let result = LIMIT.size(Some(size)).unwrap_err();
+            let error = &result.errors[0];
+            let meta = error.source.meta.as_ref().unwrap();
+
+            assert_eq!(result.status(), 400);
+            assert_eq!(error.source.parameter.as_deref(), Some("size"));
+            assert_eq!(meta["min"], 5);
+            assert_eq!(meta["max"], 50);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn new_max_should_leave_room_for_seek() {
+        SizeLimit::new(10, 1, i16::MAX);
+    }
+}
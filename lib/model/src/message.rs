@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use serde_json::Value;
+
+use crate::error::{ErrorDetail, ErrorResult};
+
+/// Message templates keyed by language and then by code. A template can be specific to the
+/// `meta` params of an error by suffixing the code with the sorted param names,
+/// e.g. `length:max,min`, and falls back to the plain code otherwise.
+static CATALOG: Lazy<HashMap<&str, HashMap<String, String>>> = Lazy::new(|| {
+    [
+        ("en", include_str!("messages/en.json")),
+        ("de", include_str!("messages/de.json")),
+    ]
+    .into_iter()
+    .map(|(language, json)| {
+        let messages = serde_json::from_str(json)
+            .unwrap_or_else(|_| panic!("messages/{language}.json is not a valid catalog"));
+
+        (language, messages)
+    })
+    .collect()
+});
+
+impl ErrorResult {
+    /// Fills in the `message` of every error that does not have one yet. `languages` is the
+    /// fallback chain of the request, e.g. `de-ch`, `de`, `en`, and is tried in order.
+    pub fn localize(&mut self, languages: &[String]) {
+        for error in self.errors.iter_mut().filter(|e| e.message.is_none()) {
+            error.message = languages
+                .iter()
+                .find_map(|language| message(language, error));
+        }
+    }
+}
+
+fn message(language: &str, error: &ErrorDetail) -> Option<String> {
+    let messages = CATALOG.get(language)?;
+    let code = error.code.as_str();
+    let meta = error.source.meta.as_ref();
+    let template = meta
+        .map(|meta| {
+            let mut keys = meta.keys().map(String::as_str).collect::<Vec<_>>();
+            keys.sort_unstable();
+            format!("{code}:{}", keys.join(","))
+        })
+        .and_then(|key| messages.get(&key))
+        .or_else(|| messages.get(code))?;
+
+    Some(interpolate(template, meta))
+}
+
+fn interpolate(template: &str, meta: Option<&HashMap<String, Value>>) -> String {
+    let meta = match meta {
+        Some(meta) => meta,
+        None => return template.to_owned(),
+    };
+
+    meta.iter()
+        .fold(template.to_owned(), |message, (key, value)| {
+            let value = match value {
+                Value::String(value) => value.to_owned(),
+                value => value.to_string(),
+            };
+
+            message.replace(&format!("{{{key}}}"), &value)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::Value;
+
+    use crate::error::{
+        id_not_found, version_conflict, ErrorCode, ErrorDetail, ErrorResult, ErrorSource,
+    };
+
+    fn languages(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    fn english() -> Vec<String> {
+        languages(&["en"])
+    }
+
+    fn result(code: &str, meta: &[(&str, Value)]) -> ErrorResult {
+        let meta = meta
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect::<HashMap<_, _>>();
+        let error = ErrorDetail {
+            id: None,
+            code: ErrorCode::validation(code.to_owned().into()),
+            message: None,
+            source: ErrorSource {
+                pointer: Some("/body/name".to_owned()),
+                parameter: None,
+                header: None,
+                meta: Some(meta),
+            },
+        };
+
+        ErrorResult::from(error)
+    }
+
+    #[test]
+    fn localize_should_interpolate_meta() {
+        let mut result = result("length", &[("min", 1.into()), ("max", 100.into())]);
+        result.localize(&english());
+
+        assert_eq!(
+            result.errors[0].message.as_deref(),
+            Some("Must be between 1 and 100 characters long.")
+        );
+    }
+
+    #[test]
+    fn localize_should_use_template_for_present_meta() {
+        let mut result = result("length", &[("max", 2000.into())]);
+        result.localize(&english());
+
+        assert_eq!(
+            result.errors[0].message.as_deref(),
+            Some("Must be at most 2000 characters long.")
+        );
+    }
+
+    #[test]
+    fn localize_should_interpolate_string_meta() {
+        let meta = [("index", 2.into()), ("key", "language".into())];
+        let mut result = result("duplicate", &meta);
+        result.localize(&english());
+
+        assert_eq!(
+            result.errors[0].message.as_deref(),
+            Some("Item 2 has the same language as a previous item.")
+        );
+    }
+
+    #[test]
+    fn localize_should_fall_back_to_primary_language() {
+        let mut result = id_not_found("sample", 1);
+        result.localize(&languages(&["de-ch", "de", "en"]));
+
+        assert_eq!(
+            result.errors[0].message.as_deref(),
+            Some("Wurde nicht gefunden.")
+        );
+    }
+
+    #[test]
+    fn localize_should_fall_back_to_default_language() {
+        let mut result = version_conflict("sample", 1, 3);
+        result.localize(&languages(&["fr", "en"]));
+
+        assert_eq!(
+            result.errors[0].message.as_deref(),
+            Some("Version 3 is no longer the current version.")
+        );
+    }
+
+    #[test]
+    fn localize_should_keep_existing_message() {
+        let mut result = id_not_found("sample", 1);
+        result.errors[0].message = Some("Custom".to_owned());
+        result.localize(&english());
+
+        assert_eq!(result.errors[0].message.as_deref(), Some("Custom"));
+    }
+
+    #[test]
+    fn localize_unknown_language_should_not_set_message() {
+        let mut result = id_not_found("sample", 1);
+        result.localize(&languages(&["fr"]));
+
+        assert_eq!(result.errors[0].message, None);
+    }
+
+    #[test]
+    fn localize_unknown_code_should_not_set_message() {
+        let mut result = result("unknown", &[]);
+        result.localize(&english());
+
+        assert_eq!(result.errors[0].message, None);
+    }
+}
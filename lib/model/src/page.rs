@@ -1,9 +1,12 @@
 use serde::Serialize;
 
+use crate::limit::SizeLimit;
+
 pub struct PageRequest {
     pub page: i64,
     pub size: i16,
     pub offset: i64,
+    pub size_limit: SizeLimit,
 }
 
 #[derive(Serialize)]
@@ -12,6 +15,8 @@ pub struct Page<T> {
     pub page: i64,
     pub size: i16,
     pub total: i64,
+    #[serde(rename = "sizeLimit")]
+    pub size_limit: SizeLimit,
 }
 
 impl<T> Page<T> {
@@ -21,6 +26,7 @@ impl<T> Page<T> {
             page: page_request.page,
             size: page_request.size,
             total: count,
+            size_limit: page_request.size_limit,
         }
     }
 }
@@ -0,0 +1,46 @@
+/// The versions of a record that an `If-Match` header accepts, e.g. `"7-2", "7-3"`, or any
+/// version with `*`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IfMatch {
+    Any,
+    Versions(Vec<i16>),
+}
+
+impl IfMatch {
+    pub fn matches(&self, version: i16) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Versions(versions) => versions.contains(&version),
+        }
+    }
+
+    /// The versions to bind to a version check, `None` for any version.
+    pub fn versions(&self) -> Option<&[i16]> {
+        match self {
+            Self::Any => None,
+            Self::Versions(versions) => Some(versions),
+        }
+    }
+
+    /// The newest accepted version, which a `version_conflict` reports. `None` for any version,
+    /// whose check only fails when the record is not found.
+    pub fn version(&self) -> Option<i16> {
+        self.versions()?.iter().copied().max()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IfMatch;
+
+    #[test]
+    fn if_match_should_match_listed_versions() {
+        let versions = IfMatch::Versions(vec![2, 3]);
+
+        assert!(versions.matches(3));
+        assert!(!versions.matches(4));
+        assert!(IfMatch::Any.matches(4));
+        assert_eq!(versions.version(), Some(3));
+        assert_eq!(IfMatch::Any.version(), None);
+    }
+}
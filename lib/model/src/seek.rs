@@ -2,13 +2,14 @@ use serde::Serialize;
 use serde_with::skip_serializing_none;
 use time::OffsetDateTime;
 
-use crate::serde::serialize_option_offset_date_time;
+use crate::{limit::SizeLimit, serde::serialize_option_offset_date_time};
 
 pub struct SeekRequest {
     pub size: i16,
     pub limit: i16,
     pub created_at: Option<OffsetDateTime>,
     pub id: Option<i64>,
+    pub size_limit: SizeLimit,
 }
 
 pub trait Seekable {
@@ -25,6 +26,7 @@ pub struct Seek<T> {
     #[serde(serialize_with = "serialize_option_offset_date_time")]
     pub created_at: Option<OffsetDateTime>,
     pub id: Option<i64>,
+    pub size_limit: SizeLimit,
 }
 
 impl<T: Seekable> Seek<T> {
@@ -43,6 +45,7 @@ impl<T: Seekable> Seek<T> {
             size: seek_request.size,
             created_at,
             id,
+            size_limit: seek_request.size_limit,
         }
     }
 }
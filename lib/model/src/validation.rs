@@ -3,17 +3,33 @@ use std::{
     collections::{BTreeMap, HashMap, HashSet},
 };
 
-use crate::error::{ErrorDetail, ErrorResult, ErrorSource};
+use crate::error::{ErrorCode, ErrorDetail, ErrorResult, ErrorSource};
 use crate::translation::Translation;
 use rust_decimal::Decimal;
 use serde_json::Value;
 use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
 
+/// Where the validated value came from, so that each error points back to the invalid field.
+#[derive(Debug, Clone, Copy)]
+enum Location {
+    Body,
+    Query,
+}
+
 pub fn validate<T: Validate>(value: T) -> Result<T, ErrorResult> {
-    value.validate().map_err(|errors| ErrorResult {
-        status: 400,
-        errors: map_validation_error(errors),
-    })?;
+    validate_at(value, Location::Body)
+}
+
+/// Same as `validate` for values read from the query string. The errors have the name of the
+/// invalid field as the `parameter` instead of a `pointer`.
+pub fn validate_parameters<T: Validate>(value: T) -> Result<T, ErrorResult> {
+    validate_at(value, Location::Query)
+}
+
+fn validate_at<T: Validate>(value: T, location: Location) -> Result<T, ErrorResult> {
+    value
+        .validate()
+        .map_err(|errors| ErrorResult::new(map_validation_error(errors, location)))?;
 
     Ok(value)
 }
@@ -71,27 +87,31 @@ pub fn validate_unique_translation<T: Translation>(value: &[T]) -> Result<(), Va
     Ok(())
 }
 
-fn map_validation_error(errors: ValidationErrors) -> Vec<ErrorDetail> {
+fn map_validation_error(errors: ValidationErrors, location: Location) -> Vec<ErrorDetail> {
     errors
         .errors()
         .iter()
         .flat_map(|(field, error)| match error {
-            ValidationErrorsKind::Struct(errors) => map_struct_errors(errors),
-            ValidationErrorsKind::List(errors) => map_list_errors(field, errors),
-            ValidationErrorsKind::Field(errors) => map_field_errors(field, errors),
+            ValidationErrorsKind::Struct(errors) => map_struct_errors(errors, location),
+            ValidationErrorsKind::List(errors) => map_list_errors(field, errors, location),
+            ValidationErrorsKind::Field(errors) => map_field_errors(field, errors, location),
         })
         .collect()
 }
 
-fn map_struct_errors(errors: &ValidationErrors) -> Vec<ErrorDetail> {
+fn map_struct_errors(errors: &ValidationErrors, location: Location) -> Vec<ErrorDetail> {
     errors
         .field_errors()
         .into_iter()
-        .flat_map(|(field, error)| map_field_errors(field, error))
+        .flat_map(|(field, error)| map_field_errors(field, error, location))
         .collect()
 }
 
-fn map_list_errors(field: &str, map: &BTreeMap<usize, Box<ValidationErrors>>) -> Vec<ErrorDetail> {
+fn map_list_errors(
+    field: &str,
+    map: &BTreeMap<usize, Box<ValidationErrors>>,
+    location: Location,
+) -> Vec<ErrorDetail> {
     map.iter()
         .flat_map(|(index, errors)| {
             errors
@@ -99,21 +119,24 @@ fn map_list_errors(field: &str, map: &BTreeMap<usize, Box<ValidationErrors>>) ->
                 .into_iter()
                 .flat_map(move |(key, error)| {
                     let field = format!("{field}/{index}/{key}");
-                    map_field_errors(&field, error)
+                    map_field_errors(&field, error, location)
                 })
         })
         .collect()
 }
 
-fn map_field_errors(field: &str, errors: &[ValidationError]) -> Vec<ErrorDetail> {
-    let pointer = format!("/body/{field}");
+fn map_field_errors(
+    field: &str,
+    errors: &[ValidationError],
+    location: Location,
+) -> Vec<ErrorDetail> {
     errors
         .iter()
-        .map(|err| map_error_detail(&pointer, err))
+        .map(|err| map_error_detail(field, err, location))
         .collect()
 }
 
-fn map_error_detail(pointer: &str, err: &ValidationError) -> ErrorDetail {
+fn map_error_detail(field: &str, err: &ValidationError, location: Location) -> ErrorDetail {
     let params: HashMap<String, Value> = err
         .params
         .clone()
@@ -129,11 +152,12 @@ fn map_error_detail(pointer: &str, err: &ValidationError) -> ErrorDetail {
 
     ErrorDetail {
         id: None,
-        code: err.code.to_string(),
+        code: ErrorCode::validation(err.code.clone()),
+        message: err.message.as_ref().map(|message| message.to_string()),
         source: ErrorSource {
-            pointer: Some(pointer.to_string()),
+            pointer: matches!(location, Location::Body).then(|| format!("/body/{field}")),
             header: None,
-            parameter: None,
+            parameter: matches!(location, Location::Query).then(|| field.to_owned()),
             meta,
         },
     }
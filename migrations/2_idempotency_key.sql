@@ -0,0 +1,16 @@
+-- Table: idempotency_key
+-- The response of each request that was sent with an `Idempotency-Key`, stored in the same
+-- transaction as the change. Rows older than a day can be deleted.
+create table idempotency_key (
+    scope text not null,
+    created_by text not null,
+    key character varying(255) not null,
+    fingerprint text not null,
+    status smallint,
+    response jsonb,
+    created_at timestamp with time zone not null default now(),
+    constraint idempotency_key_pkey primary key (scope, created_by, key)
+);
+
+-- Index: idempotency_key.created_at
+create index idempotency_key_created_at_idx on idempotency_key(created_at);
@@ -0,0 +1,153 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    env,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::{body::Incoming, server::conn::http1, service::service_fn};
+use hyper_util::rt::TokioIo;
+use lambda::{jwt::init_verifier, router::Router, tracing::init_tracing};
+use lambda_http::{
+    aws_lambda_events::{
+        apigw::{
+            ApiGatewayRequestAuthorizer, ApiGatewayRequestAuthorizerJwtDescription,
+            ApiGatewayV2httpRequestContext, ApiGatewayV2httpRequestContextHttpDescription,
+        },
+        query_map::QueryMap,
+    },
+    request::RequestContext,
+    Body, Error, Request, RequestExt, Response,
+};
+use sample::{
+    route::{SampleRoute, ADMIN_ROUTES, CUSTOMER_ROUTES},
+    service::SampleService,
+};
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+const ADDRESS_DEFAULT: &str = "127.0.0.1:3000";
+const CLAIMS_DEFAULT: &str = r#"{"sub":"local","cognito:groups":"[admin]"}"#;
+
+static REQUEST_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// Everything a request needs, created once when the server starts.
+struct State {
+    router: Router<SampleRoute>,
+    service: SampleService,
+    /// `None` if bearer tokens are verified in-process instead.
+    claims: Option<HashMap<String, String>>,
+}
+
+/// Serves every handler over plain HTTP with the same routes as the API stacks.
+/// `LOCAL_ADDRESS` is the address to listen on and `LOCAL_JWT_CLAIMS` the JSON object of the
+/// claims that the JWT authorizer would have passed, e.g. `{"sub":"user-1"}`. If `JWT_ISSUER` is
+/// set, the bearer token of each request is verified instead.
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    init_tracing();
+
+    let address = env::var("LOCAL_ADDRESS").unwrap_or_else(|_| ADDRESS_DEFAULT.to_owned());
+    let claims = env::var("LOCAL_JWT_CLAIMS").unwrap_or_else(|_| CLAIMS_DEFAULT.to_owned());
+    let claims = serde_json::from_str(&claims)
+        .expect("LOCAL_JWT_CLAIMS is not a JSON object of string claims");
+    let claims = (!init_verifier().await?).then_some(claims);
+    let routes = [ADMIN_ROUTES, CUSTOMER_ROUTES].concat();
+    let state: &'static State = Box::leak(Box::new(State {
+        router: Router::new(&routes),
+        service: SampleService::new().await?,
+        claims,
+    }));
+    let listener = TcpListener::bind(&address).await?;
+
+    info!("Listening on http://{address}");
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let service = service_fn(move |request| handle(state, request));
+
+        tokio::spawn(async move {
+            let connection = http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+
+            if let Err(err) = connection.await {
+                error!(target: "server", "Unable to serve the connection. {:?}", err);
+            }
+        });
+    }
+}
+
+async fn handle(
+    state: &'static State,
+    request: hyper::Request<Incoming>,
+) -> Result<hyper::Response<Full<Bytes>>, Infallible> {
+    let response = match to_lambda_request(request, state.claims.as_ref()).await {
+        Ok(request) => SampleRoute::dispatch(&state.router, &state.service, request).await,
+        Err(err) => Err(err),
+    };
+
+    Ok(response.map(to_hyper_response).unwrap_or_else(|err| {
+        error!(target: "server", "Unable to handle the request. {:?}", err);
+
+        hyper::Response::builder()
+            .status(500)
+            .body(Full::default())
+            .unwrap_or_default()
+    }))
+}
+
+/// Builds the request the same way the Lambda runtime does from an API Gateway HTTP API event.
+async fn to_lambda_request(
+    request: hyper::Request<Incoming>,
+    claims: Option<&HashMap<String, String>>,
+) -> Result<Request, Error> {
+    let (parts, body) = request.into_parts();
+    let bytes = body.collect().await?.to_bytes();
+    let body = if bytes.is_empty() {
+        Body::Empty
+    } else {
+        match String::from_utf8(bytes.to_vec()) {
+            Ok(text) => Body::Text(text),
+            Err(err) => Body::Binary(err.into_bytes()),
+        }
+    };
+    let query = parts
+        .uri
+        .query()
+        .unwrap_or_default()
+        .parse::<QueryMap>()
+        .unwrap_or_default();
+    let count = REQUEST_COUNT.fetch_add(1, Ordering::Relaxed);
+    let context = ApiGatewayV2httpRequestContext {
+        request_id: Some(format!("local-{count}")),
+        authorizer: claims.map(|claims| ApiGatewayRequestAuthorizer {
+            jwt: Some(ApiGatewayRequestAuthorizerJwtDescription {
+                claims: claims.clone(),
+                scopes: None,
+            }),
+            ..Default::default()
+        }),
+        http: ApiGatewayV2httpRequestContextHttpDescription {
+            method: parts.method.clone(),
+            path: Some(parts.uri.path().to_owned()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    Ok(Request::from_parts(parts, body)
+        .with_query_string_parameters(query)
+        .with_request_context(RequestContext::ApiGatewayV2(context)))
+}
+
+fn to_hyper_response(response: Response<Body>) -> hyper::Response<Full<Bytes>> {
+    let (parts, body) = response.into_parts();
+    let bytes = match body {
+        Body::Empty => Bytes::new(),
+        Body::Text(text) => Bytes::from(text),
+        Body::Binary(binary) => Bytes::from(binary),
+    };
+
+    hyper::Response::from_parts(parts, Full::new(bytes))
+}
@@ -1,11 +1,54 @@
-import { Api, StackContext, use } from "sst/constructs";
+import { FunctionUrlAuthType, HttpMethod, InvokeMode } from "aws-cdk-lib/aws-lambda";
+import { Api, Function, StackContext, use } from "sst/constructs";
 import { Database } from "../Database";
 import { AdminAuth } from "./AdminAuth";
 
 export function AdminApi({ stack }: StackContext) {
-  const { auth } = use(AdminAuth);
+  const { auth, writeRole } = use(AdminAuth);
   const database = use(Database);
+  // API Gateway buffers the whole response, so the export streams through a function URL.
+  // Without an authorizer the function verifies the bearer token itself.
+  const exporter = new Function(stack, "AdminSampleExport", {
+    handler: "./api_admin_sample_export.rs",
+    description: "Admin: Stream every sample record.",
+    bind: [...Object.values(database)],
+    // Streaming every sample takes longer than an API request, but the rows are not buffered.
+    timeout: "5 minutes",
+    memorySize: "512 MB",
+    environment: {
+      JWT_ISSUER: `https://cognito-idp.${stack.region}.amazonaws.com/${auth.userPoolId}`,
+      JWT_AUDIENCE: auth.userPoolClientId,
+    },
+  });
+  const exportUrl = exporter.addFunctionUrl({
+    authType: FunctionUrlAuthType.NONE,
+    invokeMode: InvokeMode.RESPONSE_STREAM,
+    cors: {
+      allowedOrigins: ["*"],
+      allowedHeaders: ["authorization", "accept"],
+      allowedMethods: [HttpMethod.GET],
+      exposedHeaders: ["location", "content-language", "x-request-id"],
+    },
+  });
+  // One function for every route instead of one per route, e.g. for low traffic stages.
+  const router =
+    process.env.ROUTER_MODE === "true"
+      ? new Function(stack, "AdminSample", {
+          handler: "./api_admin_sample.rs",
+          description: "Admin: Every sample route.",
+          bind: [...Object.values(database)],
+          environment: {
+            EXPORT_URL: exportUrl.url,
+            ADMIN_WRITE_ROLE: writeRole,
+          },
+        })
+      : undefined;
   const api = new Api(stack, "Admin", {
+    // Clients need the ETag of a record to send it back in If-Match, the Location of a created
+    // record, its Content-Language and the x-request-id to report an error.
+    cors: {
+      exposeHeaders: ["etag", "location", "content-language", "x-request-id"],
+    },
     authorizers: {
       jwt: {
         type: "user_pool",
@@ -19,34 +62,46 @@ export function AdminApi({ stack }: StackContext) {
       authorizer: "jwt",
       function: {
         bind: [...Object.values(database)],
+        // Exports above the size limit are a 413 that points to the streaming export. Only users
+        // of the ADMIN_WRITE_ROLE group may change samples.
+        environment: {
+          EXPORT_URL: exportUrl.url,
+          ADMIN_WRITE_ROLE: writeRole,
+        },
       },
     },
     routes: {
-      "GET /api/admin/samples": {
+      "GET /api/admin/samples": router ?? {
         function: {
           handler: "./api_admin_sample_page.rs",
           description: "Admin: Page of sample records.",
         },
       },
-      "POST /api/admin/samples": {
+      "POST /api/admin/samples": router ?? {
         function: {
           handler: "./api_admin_sample_create.rs",
           description: "Admin: Create a sample record.",
         },
       },
-      "GET /api/admin/samples/{id}": {
+      "GET /api/admin/samples/{id}": router ?? {
         function: {
           handler: "./api_admin_sample_get.rs",
           description: "Admin: Get a single sample record.",
         },
       },
-      "PUT /api/admin/samples/{id}": {
+      "PUT /api/admin/samples/{id}": router ?? {
         function: {
           handler: "./api_admin_sample_update.rs",
           description: "Admin: Update a specific single sample record.",
         },
       },
-      "DELETE /api/admin/samples/{id}": {
+      "PATCH /api/admin/samples/{id}": router ?? {
+        function: {
+          handler: "./api_admin_sample_patch.rs",
+          description: "Admin: Patch a specific single sample record.",
+        },
+      },
+      "DELETE /api/admin/samples/{id}": router ?? {
         function: {
           handler: "./api_admin_sample_delete.rs",
           description: "Admin: Soft delete a specific single sample record.",
@@ -62,9 +117,9 @@ export function AdminApi({ stack }: StackContext) {
     },
   });
   auth.attachPermissionsForAuthUsers(stack, [api]);
-
   stack.addOutputs({
     ApiEndpoint: api.url,
+    ExportUrl: exportUrl.url,
   });
 
   return { api };
@@ -1,3 +1,4 @@
+import { CfnUserPoolGroup } from "aws-cdk-lib/aws-cognito";
 import { Cognito, StackContext } from "sst/constructs";
 
 export function AdminAuth({ stack, app }: StackContext) {
@@ -18,6 +19,13 @@ export function AdminAuth({ stack, app }: StackContext) {
     },
     login: ["email"],
   });
+  // The group that may change samples, which the admin functions read from ADMIN_WRITE_ROLE.
+  const writeRole = "admin";
+  new CfnUserPoolGroup(stack, "AdminGroup", {
+    groupName: writeRole,
+    description: "Users that can create, update and delete records.",
+    userPoolId: auth.userPoolId,
+  });
   auth.cdk.userPool.addDomain("AdminDomain", {
     cognitoDomain: {
       domainPrefix,
@@ -31,5 +39,5 @@ export function AdminAuth({ stack, app }: StackContext) {
     UserPoolClientId: auth.userPoolClientId,
   });
 
-  return { auth };
+  return { auth, writeRole };
 }
@@ -1,11 +1,25 @@
-import { Api, StackContext, use } from "sst/constructs";
+import { Api, Function, StackContext, use } from "sst/constructs";
 import { Database } from "../Database";
 import { CustomerAuth } from "./CustomerAuth";
 
 export function CustomerApi({ stack }: StackContext) {
   const { auth } = use(CustomerAuth);
   const database = use(Database);
+  // One function for every route instead of one per route, e.g. for low traffic stages.
+  const router =
+    process.env.ROUTER_MODE === "true"
+      ? new Function(stack, "CustomerSample", {
+          handler: "./api_v1_sample.rs",
+          description: "Customer: Every sample route.",
+          bind: [...Object.values(database)],
+        })
+      : undefined;
   const api = new Api(stack, "Customer", {
+    // Clients need the ETag of a record to send it back in If-None-Match, the Location and
+    // Content-Language of a record and the x-request-id to report an error.
+    cors: {
+      exposeHeaders: ["etag", "location", "content-language", "x-request-id"],
+    },
     authorizers: {
       jwt: {
         type: "user_pool",
@@ -22,13 +36,13 @@ export function CustomerApi({ stack }: StackContext) {
       },
     },
     routes: {
-      "GET /api/v1/samples": {
+      "GET /api/v1/samples": router ?? {
         function: {
           handler: "./api_v1_sample_seek.rs",
           description: "Customer: Seek pagination of sample records.",
         },
       },
-      "GET /api/v1/samples/{id}": {
+      "GET /api/v1/samples/{id}": router ?? {
         function: {
           handler: "./api_v1_sample_get.rs",
           description: "Customer: Get a single sample record.",
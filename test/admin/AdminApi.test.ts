@@ -1,4 +1,4 @@
-import { Template } from "aws-cdk-lib/assertions";
+import { Match, Template } from "aws-cdk-lib/assertions";
 import { App, getStack } from "sst/constructs";
 import { initProject } from "sst/project";
 import { test } from "vitest";
@@ -20,6 +20,7 @@ test("Created Admin api gateway", async () => {
       AllowHeaders: ["*"],
       AllowMethods: ["*"],
       AllowOrigins: ["*"],
+      ExposeHeaders: ["etag", "location", "content-language", "x-request-id"],
     },
     ProtocolType: "HTTP",
   });
@@ -43,6 +44,10 @@ test("Created Admin api gateway", async () => {
     AuthorizationType: "JWT",
     RouteKey: "PUT /api/admin/samples/{id}",
   });
+  template.hasResourceProperties("AWS::ApiGatewayV2::Route", {
+    AuthorizationType: "JWT",
+    RouteKey: "PATCH /api/admin/samples/{id}",
+  });
   template.hasResourceProperties("AWS::ApiGatewayV2::Route", {
     AuthorizationType: "JWT",
     RouteKey: "DELETE /api/admin/samples/{id}",
@@ -51,4 +56,22 @@ test("Created Admin api gateway", async () => {
     AuthorizationType: "NONE",
     RouteKey: "$default",
   });
+  template.hasResourceProperties("AWS::Lambda::Function", {
+    Description: "Admin: Update a specific single sample record.",
+    Environment: {
+      Variables: Match.objectLike({ ADMIN_WRITE_ROLE: "admin" }),
+    },
+  });
+  template.hasResourceProperties("AWS::Lambda::Url", {
+    AuthType: "NONE",
+    Cors: {
+      ExposeHeaders: ["location", "content-language", "x-request-id"],
+    },
+    InvokeMode: "RESPONSE_STREAM",
+  });
+  template.hasResourceProperties("AWS::Lambda::Function", {
+    Description: "Admin: Stream every sample record.",
+    MemorySize: 512,
+    Timeout: 300,
+  });
 });
@@ -20,6 +20,7 @@ test("Created Customer api gateway", async () => {
       AllowHeaders: ["*"],
       AllowMethods: ["*"],
       AllowOrigins: ["*"],
+      ExposeHeaders: ["etag", "location", "content-language", "x-request-id"],
     },
     ProtocolType: "HTTP",
   });